@@ -0,0 +1,33 @@
+//! Generates params structs from a directory of lexicon documents.
+//!
+//! Usage: `lexicon-codegen <LEXICON_DIR> [OUTPUT]`. The code is written to
+//! standard output when no output file is given.
+
+use serde_urlencoded_xrpc::lexicon::codegen::Options;
+use serde_urlencoded_xrpc::lexicon::Lexicons;
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+const USAGE: &str = "usage: lexicon-codegen <LEXICON_DIR> [OUTPUT]";
+
+fn main() {
+    let args: Vec<_> = env::args_os().skip(1).collect();
+    let result = match &args[..] {
+        [dir] => Lexicons::load_dir(dir)
+            .map_err(|err| err.to_string())
+            .and_then(|lexicons| {
+                io::stdout()
+                    .write_all(Options::new().generate(&lexicons).as_bytes())
+                    .map_err(|err| err.to_string())
+            }),
+        [dir, out] => Options::new()
+            .generate_dir(dir, out)
+            .map_err(|err| err.to_string()),
+        _ => Err(USAGE.to_owned()),
+    };
+    if let Err(err) = result {
+        eprintln!("lexicon-codegen: {}", err);
+        process::exit(1);
+    }
+}
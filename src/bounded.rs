@@ -0,0 +1,180 @@
+//! Range-checked integers for lexicon `integer` parameters.
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+
+/// An integer between `MIN` and `MAX`, inclusive, mirroring the `minimum`
+/// and `maximum` of a lexicon `integer`.
+///
+/// Deserializing a value outside the range is an error, and no out-of-range
+/// `Bounded` can be constructed, so none is ever serialized. The `DEFAULT`
+/// parameter, which must itself be in range, is returned by
+/// `Default::default`, so that a missing parameter takes the lexicon default
+/// when the field is marked `#[serde(default)]`.
+///
+/// ```
+/// use serde_derive::{Deserialize, Serialize};
+/// use serde_urlencoded_xrpc::bounded::Bounded;
+///
+/// #[derive(Serialize, Deserialize, Debug)]
+/// struct Params {
+///     #[serde(default)]
+///     limit: Bounded<1, 100, 50>,
+/// }
+///
+/// let params: Params = serde_urlencoded_xrpc::from_str("limit=25").unwrap();
+/// assert_eq!(params.limit.get(), 25);
+///
+/// let params: Params = serde_urlencoded_xrpc::from_str("").unwrap();
+/// assert_eq!(params.limit.get(), 50);
+///
+/// let err = serde_urlencoded_xrpc::from_str::<Params>("limit=500")
+///     .unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "field `limit`: 500 is out of range, expected 1 to 100",
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bounded<const MIN: i64, const MAX: i64, const DEFAULT: i64 = MIN>(
+    i64,
+);
+
+impl<const MIN: i64, const MAX: i64, const DEFAULT: i64>
+    Bounded<MIN, MAX, DEFAULT>
+{
+    /// The smallest allowed value.
+    pub const MIN: i64 = MIN;
+
+    /// The largest allowed value.
+    pub const MAX: i64 = MAX;
+
+    /// Fails to compile when `DEFAULT` is out of range.
+    const DEFAULT_IN_RANGE: () =
+        assert!(MIN <= DEFAULT && DEFAULT <= MAX, "default out of range");
+
+    /// Checks that `value` is in range and wraps it.
+    pub fn new(value: i64) -> Result<Self, Error> {
+        if (MIN..=MAX).contains(&value) {
+            Ok(Bounded(value))
+        } else {
+            Err(Error {
+                value: value.into(),
+                min: MIN,
+                max: MAX,
+            })
+        }
+    }
+
+    /// Returns the wrapped value.
+    pub fn get(self) -> i64 {
+        self.0
+    }
+}
+
+impl<const MIN: i64, const MAX: i64, const DEFAULT: i64> Default
+    for Bounded<MIN, MAX, DEFAULT>
+{
+    fn default() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::DEFAULT_IN_RANGE;
+        Bounded(DEFAULT)
+    }
+}
+
+impl<const MIN: i64, const MAX: i64, const DEFAULT: i64> TryFrom<i64>
+    for Bounded<MIN, MAX, DEFAULT>
+{
+    type Error = Error;
+
+    fn try_from(value: i64) -> Result<Self, Error> {
+        Bounded::new(value)
+    }
+}
+
+impl<const MIN: i64, const MAX: i64, const DEFAULT: i64>
+    From<Bounded<MIN, MAX, DEFAULT>> for i64
+{
+    fn from(value: Bounded<MIN, MAX, DEFAULT>) -> i64 {
+        value.0
+    }
+}
+
+impl<const MIN: i64, const MAX: i64, const DEFAULT: i64> fmt::Display
+    for Bounded<MIN, MAX, DEFAULT>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<const MIN: i64, const MAX: i64, const DEFAULT: i64> Serialize
+    for Bounded<MIN, MAX, DEFAULT>
+{
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.0)
+    }
+}
+
+impl<'de, const MIN: i64, const MAX: i64, const DEFAULT: i64> Deserialize<'de>
+    for Bounded<MIN, MAX, DEFAULT>
+{
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        deserializer.deserialize_i64(BoundedVisitor)
+    }
+}
+
+struct BoundedVisitor<const MIN: i64, const MAX: i64, const DEFAULT: i64>;
+
+impl<'de, const MIN: i64, const MAX: i64, const DEFAULT: i64> Visitor<'de>
+    for BoundedVisitor<MIN, MAX, DEFAULT>
+{
+    type Value = Bounded<MIN, MAX, DEFAULT>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "an integer between {} and {}", MIN, MAX)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Bounded::new(v).map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        match i64::try_from(v) {
+            Ok(v) => self.visit_i64(v),
+            Err(_) => Err(E::custom(Error {
+                value: v.into(),
+                min: MIN,
+                max: MAX,
+            })),
+        }
+    }
+}
+
+/// Error returned for an integer outside the range of a `Bounded`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    value: i128,
+    min: i64,
+    max: i64,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is out of range, expected {} to {}",
+            self.value, self.min, self.max
+        )
+    }
+}
+
+impl error::Error for Error {}
@@ -0,0 +1,691 @@
+//! Deserializes a value that serde buffers before knowing its type, as it
+//! does for `#[serde(flatten)]` fields and the variants of untagged and
+//! internally tagged enums, following the type hints of the field.
+//!
+//! Without type hints, every value is a string or, with
+//! [`infer_types`](crate::de::Options::infer_types), a boolean or integer,
+//! and a key given once is a single value. Through [`deserialize`], strings
+//! are parsed as booleans and numbers, booleans and integers are accepted as
+//! strings, and a single value is accepted as a sequence of one element.
+//! Values are deserialized as the `Deserializer` deserializes other fields,
+//! with the same options and errors, which also keep the key of the
+//! offending value.
+//!
+//! Use it on the flattened field itself, or on the fields of enum variants.
+//!
+//! ```
+//! use serde_derive::Deserialize;
+//!
+//! #[derive(Deserialize, Debug, PartialEq)]
+//! struct Params {
+//!     q: String,
+//!     #[serde(
+//!         flatten,
+//!         deserialize_with = "serde_urlencoded_xrpc::de::buffered::deserialize"
+//!     )]
+//!     filter: Filter,
+//! }
+//!
+//! #[derive(Deserialize, Debug, PartialEq)]
+//! struct Filter {
+//!     limit: u32,
+//!     uris: Vec<String>,
+//! }
+//!
+//! let params: Params =
+//!     serde_urlencoded_xrpc::from_str("q=x&limit=5&uris=a").unwrap();
+//! assert_eq!(params.filter.limit, 5);
+//! assert_eq!(params.filter.uris, ["a"]);
+//! ```
+
+use crate::de::options::ValueOptions;
+use crate::de::{Error, ErrorKind, Limit, Values};
+use serde::de::value::{
+    MapAccessDeserializer, SeqAccessDeserializer, StringDeserializer,
+};
+use serde::de::{self, Deserialize, DeserializeSeed, Visitor};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+
+thread_local! {
+    /// The value options of the innermost `Deserializer` running on this
+    /// thread, see `Scope`.
+    static OPTIONS: Cell<Option<ValueOptions>> = const { Cell::new(None) };
+}
+
+/// Makes the value options of a `Deserializer` available to [`deserialize`]
+/// while the `Deserializer` runs its visitor, as the values serde buffers
+/// from it no longer carry them.
+pub(crate) struct Scope(Option<ValueOptions>);
+
+impl Scope {
+    pub(crate) fn enter(options: ValueOptions) -> Self {
+        Scope(OPTIONS.with(|cell| cell.replace(Some(options))))
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        OPTIONS.with(|cell| cell.set(self.0));
+    }
+}
+
+/// Deserializes `T` from a deserializer of buffered values, following the
+/// type hints of `T`.
+///
+/// Values are deserialized with the options of the `Deserializer` they come
+/// from, e.g. [`strict_scalars`](crate::de::Options::strict_scalars), or
+/// with the default options outside of one.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de>,
+    D: de::Deserializer<'de>,
+{
+    T::deserialize(Buffered::new(deserializer, None))
+}
+
+/// Prefixes the message through which an [`Error`] passes to the error type
+/// of a buffering deserializer, see `carry`.
+const NAME: &str = "$serde_urlencoded_xrpc::de::Error";
+
+/// Every error kind, to decode them by name.
+const KINDS: &[ErrorKind] = &[
+    ErrorKind::InvalidNumber,
+    ErrorKind::InvalidBool,
+    ErrorKind::UnknownField,
+    ErrorKind::DuplicateField,
+    ErrorKind::MissingField,
+    ErrorKind::InvalidUtf8,
+    ErrorKind::InvalidPercentEncoding,
+    ErrorKind::LimitExceeded(Limit::InputLength),
+    ErrorKind::LimitExceeded(Limit::Pairs),
+    ErrorKind::LimitExceeded(Limit::KeyLength),
+    ErrorKind::LimitExceeded(Limit::ValueLength),
+    ErrorKind::LimitExceeded(Limit::ValuesPerKey),
+    ErrorKind::LimitExceeded(Limit::Depth),
+    ErrorKind::Custom,
+];
+
+/// Converts `err` to the error type of a buffering deserializer.
+///
+/// That type is generic, but it is [`Error`] whenever the values come from a
+/// `Deserializer`, whose `de::Error::custom` then decodes the whole error
+/// back with [`decode`]. Other error types receive its message.
+fn carry<E: de::Error>(err: Error) -> E {
+    E::custom(Carried(err))
+}
+
+struct Carried(Error);
+
+impl fmt::Display for Carried {
+    /// Writes the message of the error, or the whole error encoded for
+    /// `decode` in the alternate form, e.g. `{:#}`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            return self.0.fmt(f);
+        }
+        let err = &self.0;
+        let len = |s: &Option<String>| s.as_ref().map(String::len);
+        write!(f, "{}{:?}", NAME, err.kind)?;
+        for number in &[len(&err.key), len(&err.value), err.index, err.offset] {
+            match number {
+                Some(number) => write!(f, ";{}", number)?,
+                None => f.write_str(";-")?,
+            }
+        }
+        let key = err.key.as_deref().unwrap_or_default();
+        let value = err.value.as_deref().unwrap_or_default();
+        write!(f, ";{}{}{}", key, value, err.msg)
+    }
+}
+
+/// Decodes an error encoded by `Carried`, given the alternate form of the
+/// message passed to `de::Error::custom`.
+pub(crate) fn decode(msg: &str) -> Option<Error> {
+    let mut fields = msg.strip_prefix(NAME)?.splitn(6, ';');
+    let kind = fields.next()?;
+    let mut number = || match fields.next()? {
+        "-" => Some(None),
+        number => number.parse().ok().map(Some),
+    };
+    let (key_len, value_len) = (number()?, number()?);
+    let (index, offset) = (number()?, number()?);
+    let mut rest = fields.next()?;
+    let mut take = |len: Option<usize>| match len {
+        Some(len) => {
+            let taken = rest.get(..len)?.to_owned();
+            rest = &rest[len..];
+            Some(Some(taken))
+        }
+        None => Some(None),
+    };
+    let (key, value) = (take(key_len)?, take(value_len)?);
+
+    Some(Error {
+        kind: KINDS
+            .iter()
+            .copied()
+            .find(|known| format!("{:?}", known) == kind)
+            .unwrap_or(ErrorKind::Custom),
+        msg: rest.to_owned(),
+        key,
+        value,
+        index,
+        offset,
+    })
+}
+
+/// A deserializer of buffered values following the type hints it is given,
+/// for the value of `key` if it is an entry of a buffered map.
+struct Buffered<D> {
+    de: D,
+    key: Option<String>,
+}
+
+impl<'de, D: de::Deserializer<'de>> Buffered<D> {
+    fn new(de: D, key: Option<String>) -> Self {
+        Buffered { de, key }
+    }
+
+    /// Visits the buffered value, deserializing it with `leaf` if it is a
+    /// single value or, for scalars, a sequence of them.
+    fn hinted<V, F>(
+        self,
+        visitor: V,
+        hint: Hint,
+        leaf: F,
+    ) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+        F: FnOnce(Values<'de>, V) -> Result<V::Value, Error>,
+    {
+        self.de.deserialize_any(Hinted {
+            visitor,
+            hint,
+            key: self.key,
+            leaf,
+        })
+    }
+}
+
+/// What the target type asked for, deciding how sequences and maps are
+/// visited.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Hint {
+    Any,
+    /// A scalar, deserialized from every value of a sequence as from the
+    /// values of a repeated key.
+    Scalar,
+    Enum,
+    Seq,
+    Option,
+    Newtype,
+}
+
+struct Hinted<V, F> {
+    visitor: V,
+    hint: Hint,
+    key: Option<String>,
+    leaf: F,
+}
+
+macro_rules! forward_with_hint {
+    ($($method:ident => $hint:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, D::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.hinted(visitor, Hint::$hint, |values, visitor| {
+                    values.$method(visitor)
+                })
+            }
+        )*
+    };
+}
+
+impl<'de, D: de::Deserializer<'de>> de::Deserializer<'de> for Buffered<D> {
+    type Error = D::Error;
+
+    forward_with_hint! {
+        deserialize_any => Any,
+        deserialize_bool => Scalar,
+        deserialize_i8 => Scalar,
+        deserialize_i16 => Scalar,
+        deserialize_i32 => Scalar,
+        deserialize_i64 => Scalar,
+        deserialize_i128 => Scalar,
+        deserialize_u8 => Scalar,
+        deserialize_u16 => Scalar,
+        deserialize_u32 => Scalar,
+        deserialize_u64 => Scalar,
+        deserialize_u128 => Scalar,
+        deserialize_f32 => Scalar,
+        deserialize_f64 => Scalar,
+        deserialize_char => Scalar,
+        deserialize_str => Scalar,
+        deserialize_string => Scalar,
+        deserialize_identifier => Scalar,
+        deserialize_bytes => Scalar,
+        deserialize_byte_buf => Scalar,
+        deserialize_unit => Scalar,
+        deserialize_option => Option,
+        deserialize_seq => Seq,
+        deserialize_map => Any,
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.hinted(visitor, Hint::Scalar, |values, visitor| {
+            values.deserialize_unit_struct(name, visitor)
+        })
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.hinted(visitor, Hint::Newtype, |values, visitor| {
+            values.deserialize_newtype_struct(name, visitor)
+        })
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.hinted(visitor, Hint::Seq, |values, visitor| {
+            values.deserialize_tuple(len, visitor)
+        })
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.hinted(visitor, Hint::Seq, |values, visitor| {
+            values.deserialize_tuple_struct(name, len, visitor)
+        })
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.hinted(visitor, Hint::Any, |values, visitor| {
+            values.deserialize_struct(name, fields, visitor)
+        })
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.hinted(visitor, Hint::Enum, |values, visitor| {
+            values.deserialize_enum(name, variants, visitor)
+        })
+    }
+
+    fn deserialize_ignored_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, D::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.de.deserialize_ignored_any(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.de.is_human_readable()
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty),)*) => {
+        $(
+            fn $method<E: de::Error>(self, v: $ty) -> Result<V::Value, E> {
+                self.visitor.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V, F> Visitor<'de> for Hinted<V, F>
+where
+    V: Visitor<'de>,
+    F: FnOnce(Values<'de>, V) -> Result<V::Value, Error>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.visitor.expecting(f)
+    }
+
+    // Values other than strings, booleans and integers come from other
+    // formats, and are visited as they are.
+    forward_visit! {
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]),
+        visit_byte_buf(Vec<u8>),
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<V::Value, E> {
+        let text = if v { "true" } else { "false" };
+        self.visit_values(vec![Cow::Borrowed(text)])
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<V::Value, E> {
+        self.visit_values(vec![Cow::Owned(v.to_string())])
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<V::Value, E> {
+        self.visit_values(vec![Cow::Owned(v.to_string())])
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<V::Value, E> {
+        self.visit_values(vec![Cow::Owned(v.to_owned())])
+    }
+
+    fn visit_borrowed_str<E: de::Error>(
+        self,
+        v: &'de str,
+    ) -> Result<V::Value, E> {
+        self.visit_values(vec![Cow::Borrowed(v)])
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<V::Value, E> {
+        self.visit_values(vec![Cow::Owned(v)])
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+        self.visitor.visit_none()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<V::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.visitor
+            .visit_some(Buffered::new(deserializer, self.key))
+    }
+
+    fn visit_newtype_struct<D>(
+        self,
+        deserializer: D,
+    ) -> Result<V::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.visitor
+            .visit_newtype_struct(Buffered::new(deserializer, self.key))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<V::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        match self.hint {
+            Hint::Scalar | Hint::Enum => {
+                let mut values = vec![];
+                while let Some(Text(text)) = seq.next_element()? {
+                    values.push(text);
+                }
+                self.visit_values(values)
+            }
+            Hint::Option => self.visitor.visit_some(Buffered::new(
+                SeqAccessDeserializer::new(seq),
+                self.key,
+            )),
+            Hint::Newtype => self.visitor.visit_newtype_struct(Buffered::new(
+                SeqAccessDeserializer::new(seq),
+                self.key,
+            )),
+            Hint::Any | Hint::Seq => {
+                self.visitor.visit_seq(BufferedSeq { seq, key: self.key })
+            }
+        }
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<V::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let map = BufferedMap { map, key: None };
+        match self.hint {
+            Hint::Option => self.visitor.visit_some(Buffered::new(
+                MapAccessDeserializer::new(map),
+                self.key,
+            )),
+            Hint::Newtype => self.visitor.visit_newtype_struct(Buffered::new(
+                MapAccessDeserializer::new(map),
+                self.key,
+            )),
+            Hint::Enum => {
+                self.visitor.visit_enum(MapAccessDeserializer::new(map))
+            }
+            _ => self.visitor.visit_map(map),
+        }
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<V::Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        self.visitor.visit_enum(data)
+    }
+}
+
+impl<'de, V, F> Hinted<V, F>
+where
+    V: Visitor<'de>,
+    F: FnOnce(Values<'de>, V) -> Result<V::Value, Error>,
+{
+    /// Deserializes the given values as a `Deserializer` would have, had
+    /// they been given for `key`.
+    fn visit_values<E: de::Error>(
+        self,
+        values: Vec<Cow<'de, str>>,
+    ) -> Result<V::Value, E> {
+        let options = OPTIONS.with(Cell::get).unwrap_or_default();
+        let values = Values {
+            key: Cow::Owned(self.key.clone().unwrap_or_default()),
+            values: values.into_iter().enumerate().collect(),
+            options,
+        };
+        let has_key = self.key.is_some();
+        (self.leaf)(values, self.visitor).map_err(|mut err| {
+            // Buffered values have no pair index, nor a key outside of maps.
+            err.index = None;
+            if !has_key && err.key.as_deref() == Some("") {
+                err.key = None;
+            }
+            carry(err)
+        })
+    }
+}
+
+/// A buffered string, boolean or integer, as its text.
+struct Text<'de>(Cow<'de, str>);
+
+impl<'de> Deserialize<'de> for Text<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(TextVisitor)
+    }
+}
+
+struct TextVisitor;
+
+impl<'de> Visitor<'de> for TextVisitor {
+    type Value = Text<'de>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a single value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Text<'de>, E> {
+        Ok(Text(Cow::Borrowed(if v { "true" } else { "false" })))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Text<'de>, E> {
+        Ok(Text(Cow::Owned(v.to_string())))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Text<'de>, E> {
+        Ok(Text(Cow::Owned(v.to_string())))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Text<'de>, E> {
+        Ok(Text(Cow::Owned(v.to_owned())))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(
+        self,
+        v: &'de str,
+    ) -> Result<Text<'de>, E> {
+        Ok(Text(Cow::Borrowed(v)))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Text<'de>, E> {
+        Ok(Text(Cow::Owned(v)))
+    }
+}
+
+struct BufferedSeed<T> {
+    seed: T,
+    key: Option<String>,
+}
+
+impl<'de, T: DeserializeSeed<'de>> DeserializeSeed<'de> for BufferedSeed<T> {
+    type Value = T::Value;
+
+    fn deserialize<D>(self, de: D) -> Result<T::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.seed.deserialize(Buffered::new(de, self.key))
+    }
+}
+
+struct BufferedSeq<A> {
+    seq: A,
+    key: Option<String>,
+}
+
+impl<'de, A: de::SeqAccess<'de>> de::SeqAccess<'de> for BufferedSeq<A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, A::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.seq.next_element_seed(BufferedSeed {
+            seed,
+            key: self.key.clone(),
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.seq.size_hint()
+    }
+}
+
+struct BufferedMap<A> {
+    map: A,
+    /// The key of the entry whose value is read next.
+    key: Option<String>,
+}
+
+impl<'de, A: de::MapAccess<'de>> de::MapAccess<'de> for BufferedMap<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let key = self.map.next_key_seed(BufferedSeed {
+            seed: PhantomData::<String>,
+            key: None,
+        })?;
+        match key {
+            Some(key) => {
+                self.key = Some(key.clone());
+                seed.deserialize(Buffered::new(
+                    StringDeserializer::new(key),
+                    None,
+                ))
+                .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T>(&mut self, seed: T) -> Result<T::Value, A::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.map.next_value_seed(BufferedSeed {
+            seed,
+            key: self.key.take(),
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.map.size_hint()
+    }
+}
@@ -0,0 +1,153 @@
+//! Configurable decoding of `application/x-www-form-urlencoded` input.
+
+use crate::de::{Error, ErrorKind};
+
+use std::borrow::Cow;
+use std::str;
+
+/// Like `form_urlencoded::Parse`, but with control over how `+` and
+/// malformed input are handled.
+///
+/// In strict mode, malformed percent-encoded sequences and invalid UTF-8 are
+/// errors instead of being passed through literally and replaced with U+FFFD
+/// respectively. Errors report the byte offset of the offending sequence in
+/// the input, and iteration stops after the first one.
+pub(crate) struct DecodeParse<'de> {
+    input: &'de [u8],
+    offset: usize,
+    strict: bool,
+    plus_as_space: bool,
+}
+
+impl<'de> DecodeParse<'de> {
+    pub(crate) fn new(
+        input: &'de [u8],
+        strict: bool,
+        plus_as_space: bool,
+    ) -> Self {
+        DecodeParse {
+            input,
+            offset: 0,
+            strict,
+            plus_as_space,
+        }
+    }
+
+    /// Decodes a key or value that starts at `offset` in the input.
+    fn decode(
+        &self,
+        input: &'de [u8],
+        offset: usize,
+    ) -> Result<Cow<'de, str>, Error> {
+        let needs_decoding =
+            |b: &u8| *b == b'%' || (*b == b'+' && self.plus_as_space);
+        if !input.iter().any(needs_decoding) {
+            return match str::from_utf8(input) {
+                Ok(input) => Ok(Cow::Borrowed(input)),
+                Err(err) if self.strict => {
+                    Err(invalid_utf8(offset + err.valid_up_to()))
+                }
+                Err(_) => Ok(String::from_utf8_lossy(input)),
+            };
+        }
+
+        let mut decoded = Vec::with_capacity(input.len());
+        let mut i = 0;
+        while i < input.len() {
+            match input[i] {
+                b'+' if self.plus_as_space => decoded.push(b' '),
+                b'%' => {
+                    let hex = input.get(i + 1..i + 3).and_then(|hex| {
+                        Some(hex_value(hex[0])? << 4 | hex_value(hex[1])?)
+                    });
+                    match hex {
+                        Some(byte) => {
+                            decoded.push(byte);
+                            i += 2;
+                        }
+                        None if self.strict => {
+                            return Err(invalid_percent_encoding(offset + i))
+                        }
+                        None => decoded.push(b'%'),
+                    }
+                }
+                byte => decoded.push(byte),
+            }
+            i += 1;
+        }
+
+        match String::from_utf8(decoded) {
+            Ok(decoded) => Ok(Cow::Owned(decoded)),
+            Err(err) if self.strict => {
+                let position = err.utf8_error().valid_up_to();
+                Err(invalid_utf8(offset + encoded_position(input, position)))
+            }
+            Err(err) => {
+                Ok(Cow::Owned(String::from_utf8_lossy(err.as_bytes()).into()))
+            }
+        }
+    }
+}
+
+impl<'de> Iterator for DecodeParse<'de> {
+    type Item = Result<(Cow<'de, str>, Cow<'de, str>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.input.is_empty() {
+                return None;
+            }
+
+            let (sequence, rest) = split_at_byte(self.input, b'&');
+            let offset = self.offset;
+            self.input = rest;
+            self.offset += sequence.len() + 1;
+            if sequence.is_empty() {
+                continue;
+            }
+
+            let (name, value) = split_at_byte(sequence, b'=');
+            let value_offset = offset + name.len() + 1;
+            let pair = self.decode(name, offset).and_then(|name| {
+                self.decode(value, value_offset).map(|value| (name, value))
+            });
+            if pair.is_err() {
+                self.input = &[];
+            }
+            return Some(pair);
+        }
+    }
+}
+
+fn split_at_byte(input: &[u8], byte: u8) -> (&[u8], &[u8]) {
+    match input.iter().position(|&b| b == byte) {
+        Some(position) => (&input[..position], &input[position + 1..]),
+        None => (input, &[]),
+    }
+}
+
+fn hex_value(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+/// Returns the position in `input` of the sequence that decodes to the byte
+/// at `position` in the decoded output.
+///
+/// Only called in strict mode, where every `%` starts a valid sequence.
+fn encoded_position(input: &[u8], position: usize) -> usize {
+    let mut i = 0;
+    for _ in 0..position {
+        i += if input[i] == b'%' { 3 } else { 1 };
+    }
+    i
+}
+
+fn invalid_percent_encoding(offset: usize) -> Error {
+    let msg = format_args!("invalid percent-encoding at byte {}", offset);
+    Error::new(ErrorKind::InvalidPercentEncoding, msg).with_offset(offset)
+}
+
+fn invalid_utf8(offset: usize) -> Error {
+    let msg = format_args!("invalid UTF-8 at byte {}", offset);
+    Error::new(ErrorKind::InvalidUtf8, msg).with_offset(offset)
+}
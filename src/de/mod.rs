@@ -0,0 +1,1346 @@
+//! Deserialization support for the `application/x-www-form-urlencoded` format.
+
+pub mod buffered;
+mod decode;
+mod nested;
+mod options;
+
+pub use self::options::{DuplicateKeys, Limit, Options};
+pub use crate::nesting::Nesting;
+pub use crate::seq_style::SeqStyle;
+
+use self::decode::DecodeParse;
+use self::nested::Node;
+use self::options::ValueOptions;
+use crate::seq_style::split_element_key;
+
+use form_urlencoded::parse;
+use form_urlencoded::Parse as UrlEncodedParse;
+use serde::de::value::MapDeserializer;
+use serde::de::Error as de_Error;
+use serde::de::{self, IntoDeserializer};
+use serde::forward_to_deserialize_any;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io::Read;
+use std::str;
+use std::vec;
+
+/// Deserializes a `application/x-www-form-urlencoded` value from a `&[u8]`.
+///
+/// ```
+/// let meal = vec![
+///     ("bread".to_owned(), "baguette".to_owned()),
+///     ("cheese".to_owned(), "comté".to_owned()),
+///     ("meat".to_owned(), "ham".to_owned()),
+///     ("fat".to_owned(), "butter".to_owned()),
+/// ];
+///
+/// assert_eq!(
+///     serde_urlencoded_xrpc::from_bytes::<Vec<(String, String)>>(
+///         b"bread=baguette&cheese=comt%C3%A9&meat=ham&fat=butter"),
+///     Ok(meal));
+/// ```
+pub fn from_bytes<'de, T>(input: &'de [u8]) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    Options::default().from_bytes(input)
+}
+
+/// Deserializes a `application/x-www-form-urlencoded` value from a `&str`.
+///
+/// ```
+/// let meal = vec![
+///     ("bread".to_owned(), "baguette".to_owned()),
+///     ("cheese".to_owned(), "comté".to_owned()),
+///     ("meat".to_owned(), "ham".to_owned()),
+///     ("fat".to_owned(), "butter".to_owned()),
+/// ];
+///
+/// assert_eq!(
+///     serde_urlencoded_xrpc::from_str::<Vec<(String, String)>>(
+///         "bread=baguette&cheese=comt%C3%A9&meat=ham&fat=butter"),
+///     Ok(meal));
+/// ```
+pub fn from_str<'de, T>(input: &'de str) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    Options::default().from_str(input)
+}
+
+/// Convenience function that reads all bytes from `reader` and deserializes
+/// them with `from_bytes`.
+pub fn from_reader<T, R>(reader: R) -> Result<T, Error>
+where
+    T: de::DeserializeOwned,
+    R: Read,
+{
+    Options::default().from_reader(reader)
+}
+
+/// A deserializer for the `application/x-www-form-urlencoded` format.
+///
+/// * Supported top-level outputs are structs, maps and sequences of pairs,
+///   with or without a given length.
+///
+/// * Main `deserialize` methods defers to `deserialize_map`.
+///
+/// * When deserializing a struct or map, all values of a repeated key are
+///   grouped together, so that the field can be a sequence. Sequences may
+///   also be given as set by [`Options::seq_style`].
+///
+/// * Everything else but `deserialize_seq` and `deserialize_seq_fixed_size`
+///   defers to `deserialize`.
+pub struct Deserializer<'de> {
+    input: Input<'de>,
+    options: Options,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Returns a new `Deserializer`.
+    pub fn new(parser: UrlEncodedParse<'de>) -> Self {
+        Deserializer::with_options(parser, Options::default())
+    }
+
+    /// Returns a new `Deserializer` using the given options.
+    ///
+    /// The input has already been decoded by `parser`, so
+    /// [`Options::strict_decoding`] and [`Options::plus_as_space`] have no
+    /// effect.
+    pub fn with_options(
+        parser: UrlEncodedParse<'de>,
+        options: Options,
+    ) -> Self {
+        Deserializer {
+            input: Input::Parse(parser),
+            options,
+        }
+    }
+
+    pub(crate) fn from_bytes_with_options(
+        input: &'de [u8],
+        options: Options,
+    ) -> Self {
+        let input = if options.strict_decoding || !options.plus_as_space {
+            Input::Decode(DecodeParse::new(
+                input,
+                options.strict_decoding,
+                options.plus_as_space,
+            ))
+        } else {
+            Input::Parse(parse(input))
+        };
+        Deserializer { input, options }
+    }
+
+    /// Returns the default options, to be customized before deserializing.
+    ///
+    /// ```
+    /// use serde_urlencoded_xrpc::de::DuplicateKeys;
+    /// use serde_urlencoded_xrpc::Deserializer;
+    ///
+    /// let pairs: Vec<(String, u32)> = Deserializer::builder()
+    ///     .duplicate_keys(DuplicateKeys::First)
+    ///     .from_str("limit=10")
+    ///     .unwrap();
+    /// ```
+    pub fn builder() -> Options {
+        Options::default()
+    }
+
+    fn into_pairs(
+        self,
+    ) -> Result<MapDeserializer<'de, PartIterator<'de>, Error>, Error> {
+        let pairs = Pairs::new(self.input, &self.options)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(MapDeserializer::new(PartIterator(
+            pairs.into_iter(),
+            self.options.values,
+        )))
+    }
+
+    /// Returns every pair in input order, as for `into_pairs`, checking
+    /// `max_values_per_key` although values are not grouped by key.
+    fn into_counted_pairs(
+        self,
+    ) -> Result<MapDeserializer<'de, PartIterator<'de>, Error>, Error> {
+        let max = match self.options.max_values_per_key {
+            Some(max) => max,
+            None => return self.into_pairs(),
+        };
+        let value_options = self.options.values;
+        let pairs = Pairs::new(self.input, &self.options)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (index, (key, _)) in pairs.iter().enumerate() {
+            let count = counts.entry(&**key).or_default();
+            if *count >= max {
+                let msg = format_args!("key has more than {} values", max);
+                let kind = ErrorKind::LimitExceeded(Limit::ValuesPerKey);
+                return Err(Error::new(kind, msg)
+                    .with_key(key)
+                    .with_index(index));
+            }
+            *count += 1;
+        }
+
+        Ok(MapDeserializer::new(PartIterator(
+            pairs.into_iter(),
+            value_options,
+        )))
+    }
+
+    fn into_groups(
+        self,
+        fields: Option<&[&str]>,
+    ) -> Result<GroupIterator<'de>, Error> {
+        GroupIterator::new(
+            Pairs::new(self.input, &self.options),
+            &self.options,
+            fields,
+        )
+    }
+
+    fn into_nested(self, nesting: Nesting) -> Result<Node<'de>, Error> {
+        Node::parse(
+            Pairs::new(self.input, &self.options),
+            &self.options,
+            nesting,
+        )
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let _scope = buffered::Scope::enter(self.options.values);
+        if let Some(nesting) = self.options.nesting {
+            return self.into_nested(nesting)?.deserialize_map(visitor);
+        }
+
+        visitor.visit_map(MapDeserializer::new(self.into_groups(None)?))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let _scope = buffered::Scope::enter(self.options.values);
+        if let Some(nesting) = self.options.nesting {
+            return self
+                .into_nested(nesting)?
+                .deserialize_struct(name, fields, visitor);
+        }
+
+        let deny_unknown_fields = self.options.deny_unknown_fields;
+        let groups = self.into_groups(Some(fields))?;
+
+        if deny_unknown_fields {
+            if let Some(unknown) = groups.find_unknown(fields) {
+                return Err(Error::unknown_field(&unknown.key, fields)
+                    .with_index(unknown.values[0].0));
+            }
+        }
+
+        visitor.visit_map(MapDeserializer::new(groups))
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let _scope = buffered::Scope::enter(self.options.values);
+        visitor.visit_seq(self.into_pairs()?)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.into_pairs()?.end()?;
+        visitor.visit_unit()
+    }
+
+    /// Provides a `QueryMap` with every pair in input order, and defers to
+    /// `deserialize` for other newtype structs.
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if name == crate::query_map::NAME {
+            return visitor.visit_seq(self.into_counted_pairs()?);
+        }
+        self.deserialize_map(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool
+        u8
+        u16
+        u32
+        u64
+        u128
+        i8
+        i16
+        i32
+        i64
+        i128
+        f32
+        f64
+        char
+        str
+        string
+        option
+        bytes
+        byte_buf
+        unit_struct
+        tuple_struct
+        identifier
+        tuple
+        enum
+        ignored_any
+    }
+}
+
+/// Errors returned during deserializing from
+/// `application/x-www-form-urlencoded`.
+///
+/// Besides its [`ErrorKind`], an error records as much as is known about
+/// where it occurred: the key being deserialized, the raw (decoded) value,
+/// the index of the offending pair and, for decoding errors, the byte offset
+/// in the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    msg: String,
+    key: Option<String>,
+    value: Option<String>,
+    index: Option<usize>,
+    offset: Option<usize>,
+}
+
+/// The category of a deserialization [`Error`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A value could not be parsed as the expected number type.
+    InvalidNumber,
+    /// A value was neither `true` nor `false`.
+    InvalidBool,
+    /// A key does not correspond to any field of the target struct.
+    UnknownField,
+    /// A key given several times does not correspond to a sequence.
+    DuplicateField,
+    /// A required field is absent from the input.
+    MissingField,
+    /// The input is not valid UTF-8 once decoded.
+    InvalidUtf8,
+    /// The input contains a `%` not followed by two hexadecimal digits.
+    InvalidPercentEncoding,
+    /// The input exceeds the given configured limit.
+    LimitExceeded(Limit),
+    /// Any other error, usually reported by a `Deserialize` implementation.
+    Custom,
+}
+
+impl Error {
+    pub(crate) fn new<T: fmt::Display>(kind: ErrorKind, msg: T) -> Self {
+        Error {
+            kind,
+            msg: msg.to_string(),
+            key: None,
+            value: None,
+            index: None,
+            offset: None,
+        }
+    }
+
+    /// Returns the category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Returns the key being deserialized when this error occurred, if any.
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Returns the decoded value that could not be deserialized, if any.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// Returns the zero-based index of the offending key-value pair in the
+    /// input, if any.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Returns the byte offset in the input of the sequence that could not be
+    /// decoded, if any.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    pub(crate) fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    fn with_key(mut self, key: &str) -> Self {
+        if self.key.is_none() {
+            self.key = Some(key.to_owned());
+        }
+        self
+    }
+
+    fn with_value(mut self, value: &str) -> Self {
+        if self.value.is_none() {
+            self.value = Some(value.to_owned());
+        }
+        self
+    }
+
+    fn with_index(mut self, index: usize) -> Self {
+        if self.index.is_none() {
+            self.index = Some(index);
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, &self.key) {
+            // These messages already name the field.
+            (ErrorKind::UnknownField, _)
+            | (ErrorKind::DuplicateField, _)
+            | (ErrorKind::MissingField, _)
+            | (_, None) => self.msg.fmt(f),
+            (_, Some(key)) => write!(f, "field `{}`: {}", key, self.msg),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl de::Error for Error {
+    /// Creates a `Custom` error, or decodes an `Error` that went through a
+    /// `buffered` deserializer.
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        buffered::decode(&format!("{:#}", msg))
+            .unwrap_or_else(|| Error::new(ErrorKind::Custom, msg))
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        let msg = de::value::Error::unknown_field(field, expected);
+        Error::new(ErrorKind::UnknownField, msg).with_key(field)
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        let msg = de::value::Error::missing_field(field);
+        Error::new(ErrorKind::MissingField, msg).with_key(field)
+    }
+
+    fn duplicate_field(field: &'static str) -> Self {
+        let msg = de::value::Error::duplicate_field(field);
+        Error::new(ErrorKind::DuplicateField, msg).with_key(field)
+    }
+}
+
+/// The source of the decoded pairs of a `Deserializer`.
+enum Input<'de> {
+    Parse(UrlEncodedParse<'de>),
+    Decode(DecodeParse<'de>),
+}
+
+impl<'de> Iterator for Input<'de> {
+    type Item = Result<(Cow<'de, str>, Cow<'de, str>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Input::Parse(parser) => parser.next().map(Ok),
+            Input::Decode(parser) => parser.next(),
+        }
+    }
+}
+
+/// Iterates over the decoded pairs of the input, enforcing the configured
+/// limits on the number of pairs and on their length.
+struct Pairs<'de> {
+    input: Input<'de>,
+    count: usize,
+    max_pairs: Option<usize>,
+    max_key_len: Option<usize>,
+    max_value_len: Option<usize>,
+}
+
+impl<'de> Pairs<'de> {
+    fn new(input: Input<'de>, options: &Options) -> Self {
+        Pairs {
+            input,
+            count: 0,
+            max_pairs: options.max_pairs,
+            max_key_len: options.max_key_len,
+            max_value_len: options.max_value_len,
+        }
+    }
+}
+
+impl<'de> Iterator for Pairs<'de> {
+    type Item = Result<(Cow<'de, str>, Cow<'de, str>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.count;
+        let (key, value) = match self.input.next()? {
+            Ok(pair) => pair,
+            Err(err) => return Some(Err(err.with_index(index))),
+        };
+        self.count += 1;
+
+        if let Some(max) = self.max_pairs.filter(|&max| index >= max) {
+            let msg = format_args!("input has more than {} pairs", max);
+            let err = Error::new(ErrorKind::LimitExceeded(Limit::Pairs), msg);
+            return Some(Err(err.with_index(index)));
+        }
+        if let Some(max) = self.max_key_len.filter(|&max| key.len() > max) {
+            let msg = format_args!("key is longer than {} bytes", max);
+            let err =
+                Error::new(ErrorKind::LimitExceeded(Limit::KeyLength), msg);
+            return Some(Err(err.with_index(index)));
+        }
+        if let Some(max) = self.max_value_len.filter(|&max| value.len() > max) {
+            let msg = format_args!("value is longer than {} bytes", max);
+            let err =
+                Error::new(ErrorKind::LimitExceeded(Limit::ValueLength), msg);
+            return Some(Err(err.with_key(&key).with_index(index)));
+        }
+
+        Some(Ok((key, value)))
+    }
+}
+
+struct PartIterator<'de>(
+    vec::IntoIter<(Cow<'de, str>, Cow<'de, str>)>,
+    ValueOptions,
+);
+
+impl<'de> Iterator for PartIterator<'de> {
+    type Item = (Part<'de>, Part<'de>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let options = self.1;
+        self.0
+            .next()
+            .map(|(k, v)| (Part(k, options), Part(v, options)))
+    }
+}
+
+/// Iterates over the keys of the input in order of first appearance, along
+/// with every value given for each key.
+///
+/// Keys of sequence elements such as `tags[]` and `tags[0]` are grouped with
+/// `tags` if it is one of the struct `fields`, or for maps, if the sequence
+/// style is `Bracketed` or `Indexed`. Values with an index are then ordered
+/// by it.
+struct GroupIterator<'de>(vec::IntoIter<Values<'de>>);
+
+impl<'de> GroupIterator<'de> {
+    fn new(
+        pairs: Pairs<'de>,
+        options: &Options,
+        fields: Option<&[&str]>,
+    ) -> Result<Self, Error> {
+        let mut groups: Vec<Values<'de>> = vec![];
+        let mut indices: HashMap<Cow<'de, str>, usize> = HashMap::new();
+        // The element index of each value, for groups given any.
+        let mut element_indices: HashMap<usize, Vec<usize>> = HashMap::new();
+        let value_options = options.values.for_entries(fields.is_some());
+
+        for (index, pair) in pairs.enumerate() {
+            let (key, value) = pair?;
+            let (key, element_index) =
+                match element_key(&key, &options.values, fields) {
+                    Some((base, element_index)) => (base, element_index),
+                    None => (key, None),
+                };
+            if let Some(element_index) = element_index {
+                let group = indices.get(&key).copied().unwrap_or(groups.len());
+                let len = groups.get(group).map_or(0, |g| g.values.len());
+                let sort_keys = element_indices.entry(group).or_default();
+                // Values given before the first index keep their position.
+                sort_keys.resize(len, 0);
+                sort_keys.push(element_index);
+            } else if let Some(sort_keys) = indices
+                .get(&key)
+                .and_then(|group| element_indices.get_mut(group))
+            {
+                sort_keys.push(usize::MAX);
+            }
+            match indices.get(&key) {
+                Some(&group) => {
+                    let values = &mut groups[group].values;
+                    if let Some(max) = options
+                        .max_values_per_key
+                        .filter(|&max| values.len() >= max)
+                    {
+                        let msg =
+                            format_args!("key has more than {} values", max);
+                        let kind =
+                            ErrorKind::LimitExceeded(Limit::ValuesPerKey);
+                        return Err(Error::new(kind, msg)
+                            .with_key(&key)
+                            .with_index(index));
+                    }
+                    values.push((index, value));
+                }
+                None => {
+                    indices.insert(key.clone(), groups.len());
+                    groups.push(Values {
+                        key,
+                        values: vec![(index, value)],
+                        options: value_options,
+                    });
+                }
+            }
+        }
+
+        for (group, sort_keys) in element_indices {
+            let values = &mut groups[group].values;
+            let mut keyed: Vec<_> =
+                sort_keys.into_iter().zip(values.drain(..)).collect();
+            keyed.sort_by_key(|&(sort_key, _)| sort_key);
+            values.extend(keyed.into_iter().map(|(_, value)| value));
+        }
+
+        Ok(GroupIterator(groups.into_iter()))
+    }
+
+    fn find_unknown(&self, fields: &[&str]) -> Option<&Values<'de>> {
+        self.0
+            .as_slice()
+            .iter()
+            .find(|values| !fields.contains(&&*values.key))
+    }
+}
+
+impl<'de> Iterator for GroupIterator<'de> {
+    type Item = (Part<'de>, Values<'de>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next()
+            .map(|values| (Part(values.key.clone(), values.options), values))
+    }
+}
+
+/// Returns the key of the sequence `key` is an element of, and its index if
+/// any, if `key` should be grouped with the sequence.
+fn element_key<'de>(
+    key: &Cow<'de, str>,
+    options: &ValueOptions,
+    fields: Option<&[&str]>,
+) -> Option<(Cow<'de, str>, Option<usize>)> {
+    let (base, index) = split_element_key(key)?;
+    let grouped = match fields {
+        Some(fields) => !fields.contains(&&**key) && fields.contains(&base),
+        None => {
+            matches!(options.seq_style, SeqStyle::Bracketed | SeqStyle::Indexed)
+        }
+    };
+    if !grouped {
+        return None;
+    }
+    let base = match key {
+        Cow::Borrowed(key) => Cow::Borrowed(&key[..base.len()]),
+        Cow::Owned(_) => Cow::Owned(base.to_owned()),
+    };
+    Some((base, index))
+}
+
+/// A single key or value, along with the options affecting how it is parsed.
+struct Part<'de>(Cow<'de, str>, ValueOptions);
+
+impl<'de> Part<'de> {
+    fn deserialize_str_value<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.0 {
+            Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+            Cow::Owned(value) => visitor.visit_string(value),
+        }
+    }
+
+    fn invalid(&self, kind: ErrorKind, msg: impl fmt::Display) -> Error {
+        Error::new(kind, msg).with_value(&self.0)
+    }
+
+    /// Parses an integer, which in strict mode must be written as in the
+    /// atproto data model and fit in an `i64`.
+    fn parse_integer<T>(&self) -> Result<T, Error>
+    where
+        T: str::FromStr,
+        T::Err: fmt::Display,
+    {
+        if self.1.strict_scalars {
+            if !is_canonical_integer(&self.0) {
+                return Err(self.invalid(
+                    ErrorKind::InvalidNumber,
+                    "expected an integer without sign or leading zeros",
+                ));
+            }
+            if self.0.parse::<i64>().is_err() {
+                return Err(self.invalid(
+                    ErrorKind::InvalidNumber,
+                    "integer is out of range for a 64-bit signed integer",
+                ));
+            }
+        }
+        self.0
+            .parse()
+            .map_err(|err| self.invalid(ErrorKind::InvalidNumber, err))
+    }
+
+    /// Parses a float, which strict mode rejects.
+    fn parse_float<T>(&self) -> Result<T, Error>
+    where
+        T: str::FromStr,
+        T::Err: fmt::Display,
+    {
+        if self.1.strict_scalars {
+            return Err(self.invalid(
+                ErrorKind::InvalidNumber,
+                "floats are not supported by the atproto data model",
+            ));
+        }
+        self.0
+            .parse()
+            .map_err(|err| self.invalid(ErrorKind::InvalidNumber, err))
+    }
+
+    /// Parses `true` or `false`, or in lenient mode also `1`, `0`, `yes` and
+    /// `no`, ignoring case.
+    fn parse_bool(&self) -> Result<bool, Error> {
+        if !self.1.lenient_bools {
+            return self
+                .0
+                .parse()
+                .map_err(|err| self.invalid(ErrorKind::InvalidBool, err));
+        }
+        match &*self.0.to_ascii_lowercase() {
+            "true" | "1" | "yes" => Ok(true),
+            "false" | "0" | "no" => Ok(false),
+            _ => Err(self.invalid(
+                ErrorKind::InvalidBool,
+                "expected `true`, `false`, `1`, `0`, `yes` or `no`",
+            )),
+        }
+    }
+
+    /// Visits the value as a boolean or integer if it is unambiguously one,
+    /// and as a string otherwise.
+    fn deserialize_inferred<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match &*self.0 {
+            "true" => return visitor.visit_bool(true),
+            "false" => return visitor.visit_bool(false),
+            value if is_canonical_integer(value) => {
+                if let Ok(value) = value.parse::<u64>() {
+                    return visitor.visit_u64(value);
+                }
+                if let Ok(value) = value.parse::<i64>() {
+                    return visitor.visit_i64(value);
+                }
+            }
+            _ => {}
+        }
+        self.deserialize_str_value(visitor)
+    }
+}
+
+/// Whether `value` is an integer written without sign, leading zeros or
+/// anything else that would be lost by parsing and printing it again.
+pub(crate) fn is_canonical_integer(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    match digits.as_bytes() {
+        [b'0'] => value == "0",
+        [first, rest @ ..] => {
+            (b'1'..=b'9').contains(first)
+                && rest.iter().all(|digit| digit.is_ascii_digit())
+        }
+        [] => false,
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Part<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! forward_to_str_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+                where V: de::Visitor<'de>
+            {
+                self.deserialize_str_value(visitor)
+            }
+        )*
+    }
+}
+
+macro_rules! forward_parsed_value {
+    ($($ty:ident => $method:ident as $parse:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+                where V: de::Visitor<'de>
+            {
+                let val = self.$parse::<$ty>()?;
+                val.into_deserializer().$method(visitor)
+            }
+        )*
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Part<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.1.infer_types {
+            self.deserialize_inferred(visitor)
+        } else {
+            self.deserialize_str_value(visitor)
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.0.is_empty() && self.1.empty_as_none {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.0.is_empty() && self.1.bare_keys_as_true {
+            return visitor.visit_bool(true);
+        }
+        visitor.visit_bool(self.parse_bool()?)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_enum(ValueEnumAccess(self, variants))
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_str_value! {
+        deserialize_char
+        deserialize_str
+        deserialize_string
+        deserialize_bytes
+        deserialize_byte_buf
+        deserialize_identifier
+    }
+
+    forward_to_deserialize_any! {
+        unit
+        unit_struct
+        tuple_struct
+        struct
+        tuple
+        ignored_any
+        seq
+        map
+    }
+
+    forward_parsed_value! {
+        u8 => deserialize_u8 as parse_integer,
+        u16 => deserialize_u16 as parse_integer,
+        u32 => deserialize_u32 as parse_integer,
+        u64 => deserialize_u64 as parse_integer,
+        u128 => deserialize_u128 as parse_integer,
+        i8 => deserialize_i8 as parse_integer,
+        i16 => deserialize_i16 as parse_integer,
+        i32 => deserialize_i32 as parse_integer,
+        i64 => deserialize_i64 as parse_integer,
+        i128 => deserialize_i128 as parse_integer,
+        f32 => deserialize_f32 as parse_float,
+        f64 => deserialize_f64 as parse_float,
+    }
+}
+
+/// All values given for a single key.
+///
+/// Sequences, tuples and tuple structs consume every value, anything else
+/// requires exactly one unless the `DuplicateKeys` policy picks one.
+///
+/// Errors are annotated with the key, and with the index of the offending
+/// pair when it is known.
+struct Values<'de> {
+    key: Cow<'de, str>,
+    values: Vec<(usize, Cow<'de, str>)>,
+    options: ValueOptions,
+}
+
+impl<'de> Values<'de> {
+    fn deserialize_single<F, T>(mut self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(Part<'de>) -> Result<T, Error>,
+    {
+        let policy = self.options.duplicate_keys;
+        let (index, value) = match policy.unwrap_or(DuplicateKeys::Reject) {
+            _ if self.values.len() == 1 => self.values.pop().unwrap(),
+            DuplicateKeys::Reject => {
+                let msg = format_args!("duplicate field `{}`", self.key);
+                return Err(Error::new(ErrorKind::DuplicateField, msg)
+                    .with_key(&self.key)
+                    .with_index(self.values[1].0));
+            }
+            DuplicateKeys::First => self.values.swap_remove(0),
+            DuplicateKeys::Last => self.values.pop().unwrap(),
+        };
+
+        f(Part(value, self.options))
+            .map_err(|err| err.with_key(&self.key).with_index(index))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Values<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! forward_to_single_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+                where V: de::Visitor<'de>
+            {
+                self.deserialize_single(|part| part.$method(visitor))
+            }
+        )*
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Values<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match &*self.values {
+            [(_, value)]
+                if !(self.options.seq_style == SeqStyle::CommaSeparated
+                    && value.contains(',')) =>
+            {
+                self.deserialize_single(|part| part.deserialize_any(visitor))
+            }
+            _ => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let Values {
+            key,
+            mut values,
+            options,
+        } = self;
+        if options.seq_style == SeqStyle::CommaSeparated {
+            values = values
+                .into_iter()
+                .flat_map(|(index, value)| {
+                    split_value(value, ',', usize::MAX)
+                        .into_iter()
+                        .map(move |element| (index, element))
+                })
+                .collect();
+        }
+        let mut seq = ValuesSeqAccess {
+            values: values.into_iter(),
+            count: 0,
+            options,
+        };
+        visitor
+            .visit_seq(&mut seq)
+            .and_then(|value| seq.end().map(|()| value))
+            .map_err(|err| err.with_key(&key))
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match &*self.values {
+            [(_, value)] if value.is_empty() && self.options.empty_as_none => {
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    /// Deserializes the inner value, using the sequence style requested by a
+    /// `seq_style` module if any.
+    fn deserialize_newtype_struct<V>(
+        mut self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if let Some(style) = SeqStyle::from_newtype_name(name) {
+            self.options.seq_style = style;
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_single(|part| {
+            part.deserialize_enum(name, variants, visitor)
+        })
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_single(|part| {
+            part.deserialize_unit_struct(name, visitor)
+        })
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_single(|part| {
+            part.deserialize_struct(name, fields, visitor)
+        })
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_single_value! {
+        deserialize_bool
+        deserialize_u8
+        deserialize_u16
+        deserialize_u32
+        deserialize_u64
+        deserialize_u128
+        deserialize_i8
+        deserialize_i16
+        deserialize_i32
+        deserialize_i64
+        deserialize_i128
+        deserialize_f32
+        deserialize_f64
+        deserialize_char
+        deserialize_str
+        deserialize_string
+        deserialize_unit
+        deserialize_bytes
+        deserialize_byte_buf
+        deserialize_identifier
+        deserialize_map
+    }
+}
+
+struct ValuesSeqAccess<'de> {
+    values: vec::IntoIter<(usize, Cow<'de, str>)>,
+    count: usize,
+    options: ValueOptions,
+}
+
+impl<'de> ValuesSeqAccess<'de> {
+    fn end(self) -> Result<(), Error> {
+        let remaining = self.values.len();
+        if remaining == 0 {
+            Ok(())
+        } else {
+            Err(Error::invalid_length(
+                self.count + remaining,
+                &ExpectedInSeq(self.count),
+            ))
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for ValuesSeqAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.values.next() {
+            Some((index, value)) => {
+                self.count += 1;
+                seed.deserialize(Part(value, self.options))
+                    .map(Some)
+                    .map_err(|err| err.with_index(index))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct ExpectedInSeq(usize);
+
+impl de::Expected for ExpectedInSeq {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 1 {
+            write!(formatter, "1 element in sequence")
+        } else {
+            write!(formatter, "{} elements in sequence", self.0)
+        }
+    }
+}
+
+/// Deserializes an enum from a single value.
+///
+/// Variants with data are given as the variant name followed by each field,
+/// preceded by the variant separator, e.g. `top:24h`. A value naming one of
+/// the variants as a whole, such as `a:b` for `#[serde(rename = "a:b")]`, is
+/// that variant without data.
+struct ValueEnumAccess<'de>(Part<'de>, &'static [&'static str]);
+
+impl<'de> de::EnumAccess<'de> for ValueEnumAccess<'de> {
+    type Error = Error;
+    type Variant = ValueVariantAccess<'de>;
+
+    fn variant_seed<V>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let ValueEnumAccess(Part(value, options), variants) = self;
+        let (variant, data) = if variants.contains(&&*value) {
+            (value, None)
+        } else {
+            let mut parts = split_value(value, options.variant_separator, 2);
+            let data = if parts.len() == 2 { parts.pop() } else { None };
+            (parts.pop().unwrap_or_default(), data)
+        };
+        let variant = seed.deserialize(variant.into_deserializer())?;
+        Ok((variant, ValueVariantAccess { data, options }))
+    }
+}
+
+struct ValueVariantAccess<'de> {
+    data: Option<Cow<'de, str>>,
+    options: ValueOptions,
+}
+
+impl<'de> ValueVariantAccess<'de> {
+    fn deserialize_fields<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let options = self.options;
+        let fields = match self.data {
+            Some(data) => {
+                split_value(data, options.variant_separator, len.max(1))
+            }
+            None => vec![],
+        };
+        if fields.len() != len {
+            let expected = format!("a variant with {} fields", len);
+            return Err(Error::invalid_length(
+                fields.len(),
+                &expected.as_str(),
+            ));
+        }
+        let fields = fields.into_iter().map(|field| Part(field, options));
+        visitor.visit_seq(de::value::SeqDeserializer::new(fields))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for ValueVariantAccess<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.data {
+            None => Ok(()),
+            Some(data) => Err(Error::custom(format_args!(
+                "expected unit variant, found data `{}`",
+                data
+            ))),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.data {
+            Some(data) => seed.deserialize(Part(data, self.options)),
+            None => Err(Error::custom("expected data for newtype variant")),
+        }
+    }
+
+    fn tuple_variant<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_fields(len, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_fields(fields.len(), visitor)
+    }
+}
+
+/// Splits `value` into at most `n` parts at `separator`, borrowing from the
+/// input if possible.
+fn split_value<'de>(
+    value: Cow<'de, str>,
+    separator: char,
+    n: usize,
+) -> Vec<Cow<'de, str>> {
+    match value {
+        Cow::Borrowed(value) => {
+            value.splitn(n, separator).map(Cow::Borrowed).collect()
+        }
+        Cow::Owned(value) => value
+            .splitn(n, separator)
+            .map(|part| Cow::Owned(part.to_owned()))
+            .collect(),
+    }
+}
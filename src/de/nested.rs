@@ -0,0 +1,501 @@
+//! Deserialization of nested keys such as `filter.author` or
+//! `items[0][name]`.
+
+use crate::de::options::ValueOptions;
+use crate::de::{Error, ErrorKind, Limit, Options, Pairs, Part, Values};
+use crate::nesting::Nesting;
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::Error as _;
+use serde::de::{self, IntoDeserializer, Unexpected};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A segment of a nested key.
+enum Segment<'de> {
+    /// A field name, map key or sequence index.
+    Field(Cow<'de, str>),
+    /// `[]`, which appends to a sequence.
+    Append,
+}
+
+/// Splits a key into its segments, e.g. `items[0][name]` into `items`, `0`
+/// and `name`.
+///
+/// A key that is not well-formed for the notation is a single segment. With
+/// dots, each segment may still end with brackets, as written by
+/// `SeqStyle::Bracketed` and `SeqStyle::Indexed`, e.g. `items[0].name`.
+fn split_key<'de>(key: &Cow<'de, str>, nesting: Nesting) -> Vec<Segment<'de>> {
+    let ranges = match nesting {
+        Nesting::Dot => {
+            let mut start = 0;
+            let mut ranges = vec![];
+            let ends = key.match_indices('.').map(|(i, _)| i);
+            for end in ends.chain(Some(key.len())) {
+                match split_brackets(&key[start..end]) {
+                    Some(brackets) => {
+                        ranges.extend(brackets.into_iter().map(|range| {
+                            range.map(|r| start + r.start..start + r.end)
+                        }))
+                    }
+                    None => ranges.push(Some(start..end)),
+                }
+                start = end + 1;
+            }
+            ranges
+        }
+        Nesting::Bracket | Nesting::IndexedBracket => {
+            split_brackets(key).unwrap_or_else(|| vec![Some(0..key.len())])
+        }
+    };
+
+    let segment = |range: Option<Range<usize>>| match (range, key) {
+        (None, _) => Segment::Append,
+        (Some(range), Cow::Borrowed(key)) => {
+            Segment::Field(Cow::Borrowed(&key[range]))
+        }
+        (Some(range), Cow::Owned(key)) => {
+            Segment::Field(Cow::Owned(key[range].to_owned()))
+        }
+    };
+    ranges.into_iter().map(segment).collect()
+}
+
+/// Returns the ranges of the segments of `base[a][b]`, with `None` for `[]`.
+fn split_brackets(key: &str) -> Option<Vec<Option<Range<usize>>>> {
+    let base_end = key.find('[').filter(|&end| end > 0)?;
+    let mut ranges = vec![Some(0..base_end)];
+    let mut pos = base_end;
+    while pos < key.len() {
+        if key.as_bytes()[pos] != b'[' {
+            return None;
+        }
+        let end = pos + 1 + key[pos + 1..].find(']')?;
+        ranges.push(Some(pos + 1..end).filter(|range| !range.is_empty()));
+        pos = end + 1;
+    }
+    Some(ranges)
+}
+
+/// The values of the input arranged by their nested keys.
+pub(super) enum Node<'de> {
+    /// All values given for a key with no nested fields.
+    Leaf(Values<'de>),
+    /// Nested fields, map entries or indexed sequence elements.
+    Fields(Fields<'de>),
+    /// Sequence elements given with `[]`.
+    List(Vec<Node<'de>>),
+}
+
+pub(super) struct Fields<'de> {
+    entries: Vec<(Cow<'de, str>, Node<'de>)>,
+    indices: HashMap<Cow<'de, str>, usize>,
+    options: ValueOptions,
+    deny_unknown_fields: bool,
+}
+
+impl<'de> Node<'de> {
+    /// Arranges `pairs` into a tree of nested keys.
+    pub(super) fn parse(
+        pairs: Pairs<'de>,
+        options: &Options,
+        nesting: Nesting,
+    ) -> Result<Self, Error> {
+        let mut root = Node::Fields(Fields::new(options));
+        for (index, pair) in pairs.enumerate() {
+            let (key, value) = pair?;
+            root.insert(key.clone(), nesting, index, value, options)
+                .map_err(|err| err.with_key(&key).with_index(index))?;
+        }
+        Ok(root)
+    }
+
+    fn new(
+        next: Option<&Segment<'de>>,
+        key: Cow<'de, str>,
+        options: &Options,
+    ) -> Self {
+        match next {
+            None => Node::Leaf(Values {
+                key,
+                values: vec![],
+                options: options.values,
+            }),
+            Some(Segment::Field(_)) => Node::Fields(Fields::new(options)),
+            Some(Segment::Append) => Node::List(vec![]),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        key: Cow<'de, str>,
+        nesting: Nesting,
+        index: usize,
+        value: Cow<'de, str>,
+        options: &Options,
+    ) -> Result<(), Error> {
+        let segments = split_key(&key, nesting);
+        if segments.len() > options.max_depth {
+            let msg = format_args!(
+                "key is nested more than {} levels deep",
+                options.max_depth
+            );
+            return Err(Error::new(
+                ErrorKind::LimitExceeded(Limit::Depth),
+                msg,
+            ));
+        }
+
+        let mut node = self;
+        for (i, segment) in segments.iter().enumerate() {
+            let next = segments.get(i + 1);
+            node = match (node, segment) {
+                (Node::Fields(fields), Segment::Field(name)) => {
+                    fields.child(name, || Node::new(next, key.clone(), options))
+                }
+                (Node::List(items), Segment::Append) => {
+                    let reuse = matches!(
+                        items.last(),
+                        Some(last) if next.is_some()
+                            && !last.contains(&segments[i + 1..])
+                    );
+                    if !reuse {
+                        items.push(Node::new(next, key.clone(), options));
+                    }
+                    items.last_mut().unwrap()
+                }
+                _ => return Err(conflicting_key()),
+            };
+        }
+
+        match node {
+            Node::Leaf(leaf) => {
+                if let Some(max) = options
+                    .max_values_per_key
+                    .filter(|&max| leaf.values.len() >= max)
+                {
+                    let msg = format_args!("key has more than {} values", max);
+                    let kind = ErrorKind::LimitExceeded(Limit::ValuesPerKey);
+                    return Err(Error::new(kind, msg));
+                }
+                leaf.values.push((index, value));
+                Ok(())
+            }
+            _ => Err(conflicting_key()),
+        }
+    }
+
+    /// Whether a value was already given for the key made of `segments`,
+    /// relative to this node.
+    fn contains(&self, segments: &[Segment<'de>]) -> bool {
+        let mut node = self;
+        for segment in segments {
+            node = match (node, segment) {
+                (Node::Fields(fields), Segment::Field(name)) => {
+                    match fields.indices.get(name) {
+                        Some(&i) => &fields.entries[i].1,
+                        None => return false,
+                    }
+                }
+                (Node::List(items), Segment::Append) => match items.last() {
+                    Some(last) => last,
+                    None => return false,
+                },
+                _ => return true,
+            };
+        }
+        true
+    }
+
+    fn unexpected(&self) -> Unexpected<'static> {
+        match self {
+            Node::Leaf(_) => Unexpected::Other("value"),
+            Node::Fields(_) => Unexpected::Other("nested fields"),
+            Node::List(_) => Unexpected::Seq,
+        }
+    }
+}
+
+fn conflicting_key() -> Error {
+    Error::custom("key conflicts with the nesting of a previous key")
+}
+
+impl<'de> Fields<'de> {
+    fn new(options: &Options) -> Self {
+        Fields {
+            entries: vec![],
+            indices: HashMap::new(),
+            options: options.values,
+            deny_unknown_fields: options.deny_unknown_fields,
+        }
+    }
+
+    fn child<F>(&mut self, name: &Cow<'de, str>, make: F) -> &mut Node<'de>
+    where
+        F: FnOnce() -> Node<'de>,
+    {
+        let i = match self.indices.get(name) {
+            Some(&i) => i,
+            None => {
+                self.indices.insert(name.clone(), self.entries.len());
+                self.entries.push((name.clone(), make()));
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[i].1
+    }
+
+    fn deserialize_map<V>(
+        self,
+        struct_fields: bool,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let options = self.options;
+        let value_options = options.for_entries(struct_fields);
+        let entries = self.entries.into_iter().map(|(key, mut node)| {
+            if let Node::Leaf(values) = &mut node {
+                values.options = value_options;
+            }
+            (Part(key, options), node)
+        });
+        let mut map = MapDeserializer::new(entries);
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    /// Deserializes the entries as sequence elements, ordered by their
+    /// indices.
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let mut elements = Vec::with_capacity(self.entries.len());
+        for (key, node) in self.entries {
+            match key.parse::<usize>() {
+                Ok(index) => elements.push((index, node)),
+                Err(_) => {
+                    let msg =
+                        format_args!("expected an index, found `{}`", key);
+                    return Err(Error::custom(msg));
+                }
+            }
+        }
+        elements.sort_by_key(|&(index, _)| index);
+        let elements = elements.into_iter().map(|(_, node)| node);
+        de::Deserializer::deserialize_any(
+            SeqDeserializer::new(elements),
+            visitor,
+        )
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Node<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+macro_rules! forward_to_leaf {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+                where V: de::Visitor<'de>
+            {
+                match self {
+                    Node::Leaf(values) => values.$method(visitor),
+                    node => Err(Error::invalid_type(node.unexpected(), &visitor)),
+                }
+            }
+        )*
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Node<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(values) => values.deserialize_any(visitor),
+            Node::Fields(fields) => fields.deserialize_map(false, visitor),
+            Node::List(items) => {
+                SeqDeserializer::new(items.into_iter()).deserialize_any(visitor)
+            }
+        }
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(values) => values.deserialize_map(visitor),
+            Node::Fields(fields) => fields.deserialize_map(false, visitor),
+            node => Err(Error::invalid_type(node.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(values) => {
+                values.deserialize_struct(name, fields, visitor)
+            }
+            Node::Fields(entries) => {
+                if entries.deny_unknown_fields {
+                    let unknown = entries
+                        .entries
+                        .iter()
+                        .find(|(key, _)| !fields.contains(&&**key));
+                    if let Some((key, _)) = unknown {
+                        return Err(Error::unknown_field(key, fields));
+                    }
+                }
+                entries.deserialize_map(true, visitor)
+            }
+            node => Err(Error::invalid_type(node.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(values) => values.deserialize_seq(visitor),
+            Node::Fields(fields) => fields.deserialize_seq(visitor),
+            Node::List(items) => {
+                SeqDeserializer::new(items.into_iter()).deserialize_any(visitor)
+            }
+        }
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(values) => values.deserialize_option(visitor),
+            node => visitor.visit_some(node),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(values) => {
+                values.deserialize_newtype_struct(name, visitor)
+            }
+            node => visitor.visit_newtype_struct(node),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(values) => {
+                values.deserialize_enum(name, variants, visitor)
+            }
+            node => Err(Error::invalid_type(node.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self {
+            Node::Leaf(values) => values.deserialize_unit_struct(name, visitor),
+            node => Err(Error::invalid_type(node.unexpected(), &visitor)),
+        }
+    }
+
+    forward_to_leaf! {
+        deserialize_bool
+        deserialize_u8
+        deserialize_u16
+        deserialize_u32
+        deserialize_u64
+        deserialize_u128
+        deserialize_i8
+        deserialize_i16
+        deserialize_i32
+        deserialize_i64
+        deserialize_i128
+        deserialize_f32
+        deserialize_f64
+        deserialize_char
+        deserialize_str
+        deserialize_string
+        deserialize_unit
+        deserialize_bytes
+        deserialize_byte_buf
+        deserialize_identifier
+    }
+}
@@ -0,0 +1,392 @@
+use crate::de::{Deserializer, Error, ErrorKind, Nesting, SeqStyle};
+
+use form_urlencoded::Parse as UrlEncodedParse;
+use serde::de::{self, Error as _};
+use std::io::Read;
+
+/// Options controlling how input is mapped onto the deserialized value.
+///
+/// ```
+/// use serde_derive::Deserialize;
+/// use serde_urlencoded_xrpc::de::{DuplicateKeys, Options};
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// struct Params {
+///     limit: u32,
+/// }
+///
+/// let options = Options::new()
+///     .deny_unknown_fields(true)
+///     .duplicate_keys(DuplicateKeys::Last);
+///
+/// assert_eq!(
+///     options.from_str::<Params>("limit=1&limit=2"),
+///     Ok(Params { limit: 2 }));
+/// assert!(options.from_str::<Params>("limit=1&cursor=x").is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub(crate) deny_unknown_fields: bool,
+    pub(crate) values: ValueOptions,
+    pub(crate) max_input_len: Option<usize>,
+    pub(crate) max_pairs: Option<usize>,
+    pub(crate) max_key_len: Option<usize>,
+    pub(crate) max_value_len: Option<usize>,
+    pub(crate) max_values_per_key: Option<usize>,
+    pub(crate) strict_decoding: bool,
+    pub(crate) plus_as_space: bool,
+    pub(crate) nesting: Option<Nesting>,
+    pub(crate) max_depth: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            deny_unknown_fields: false,
+            values: ValueOptions::default(),
+            max_input_len: None,
+            max_pairs: None,
+            max_key_len: None,
+            max_value_len: None,
+            max_values_per_key: None,
+            strict_decoding: false,
+            plus_as_space: true,
+            nesting: None,
+            max_depth: 16,
+        }
+    }
+}
+
+/// The options affecting how individual values are deserialized.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ValueOptions {
+    /// `None` rejects repeated keys for struct fields, and keeps the last
+    /// value for map entries.
+    pub(crate) duplicate_keys: Option<DuplicateKeys>,
+    pub(crate) infer_types: bool,
+    pub(crate) variant_separator: char,
+    pub(crate) seq_style: SeqStyle,
+    pub(crate) empty_as_none: bool,
+    pub(crate) bare_keys_as_true: bool,
+    pub(crate) strict_scalars: bool,
+    pub(crate) lenient_bools: bool,
+}
+
+impl ValueOptions {
+    /// Returns these options with the default policy for repeated keys
+    /// applied, depending on whether they are struct fields or map entries.
+    pub(crate) fn for_entries(mut self, struct_fields: bool) -> Self {
+        self.duplicate_keys.get_or_insert(if struct_fields {
+            DuplicateKeys::Reject
+        } else {
+            DuplicateKeys::Last
+        });
+        self
+    }
+}
+
+impl Default for ValueOptions {
+    fn default() -> Self {
+        ValueOptions {
+            duplicate_keys: None,
+            infer_types: false,
+            variant_separator: ':',
+            seq_style: SeqStyle::default(),
+            empty_as_none: false,
+            bare_keys_as_true: false,
+            strict_scalars: false,
+            lenient_bools: false,
+        }
+    }
+}
+
+/// How a key given several times is handled when deserializing a field that
+/// is not a sequence.
+///
+/// Sequence fields always receive every value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DuplicateKeys {
+    /// Fails with a `DuplicateField` error.
+    Reject,
+    /// Uses the first value and ignores the others.
+    First,
+    /// Uses the last value and ignores the others.
+    Last,
+}
+
+/// A limit on the input, reported by [`ErrorKind::LimitExceeded`].
+///
+/// [`ErrorKind::LimitExceeded`]: crate::de::ErrorKind::LimitExceeded
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Limit {
+    /// The length of the whole input, in bytes.
+    InputLength,
+    /// The number of key-value pairs.
+    Pairs,
+    /// The length of a decoded key, in bytes.
+    KeyLength,
+    /// The length of a decoded value, in bytes.
+    ValueLength,
+    /// The number of values given for a single key.
+    ValuesPerKey,
+    /// The number of segments of a nested key.
+    Depth,
+}
+
+impl Options {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// Whether keys that do not correspond to any field of the target struct
+    /// are rejected, as with `#[serde(deny_unknown_fields)]`.
+    ///
+    /// Defaults to `false`. Fields of flattened structs are not known to the
+    /// deserializer, so this has no effect on structs containing them.
+    pub fn deny_unknown_fields(mut self, deny: bool) -> Self {
+        self.deny_unknown_fields = deny;
+        self
+    }
+
+    /// How keys given several times for a non-sequence field are handled.
+    ///
+    /// By default, struct fields use [`DuplicateKeys::Reject`], while maps
+    /// such as `HashMap<String, String>` use [`DuplicateKeys::Last`], as they
+    /// always have. Setting a policy applies it to both.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.values.duplicate_keys = Some(policy);
+        self
+    }
+
+    /// Whether values deserialized without a type hint are visited as
+    /// booleans and integers when they look like ones, rather than always as
+    /// strings.
+    ///
+    /// Defaults to `false`. Serde buffers values without a type hint for
+    /// `#[serde(flatten)]` fields, untagged enums and internally tagged
+    /// enums; enabling this lets numeric and boolean fields of those work.
+    /// In exchange, string fields of those receive `true`, `false` and
+    /// integers such as `123` as non-strings and fail to deserialize. Fields
+    /// with a type hint, e.g. of regular structs, are not affected.
+    ///
+    /// Either way, a key given once is a single value rather than a sequence
+    /// of one, and with [`SeqStyle::CommaSeparated`] a value containing a
+    /// comma is a sequence. [`de::buffered`](crate::de::buffered) makes such
+    /// fields follow their type hints instead, with or without this option.
+    pub fn infer_types(mut self, infer: bool) -> Self {
+        self.values.infer_types = infer;
+        self
+    }
+
+    /// The separator between the name of an enum variant and each of its
+    /// fields, e.g. `top:24h` for `Sort::Top(Window::Day)`.
+    ///
+    /// Defaults to `:`. Struct variants take their fields in declaration
+    /// order, and the last field receives the rest of the value, separators
+    /// included. Unit variants whose name contains the separator cannot be
+    /// deserialized.
+    pub fn variant_separator(mut self, separator: char) -> Self {
+        self.values.variant_separator = separator;
+        self
+    }
+
+    /// How the elements of sequence values are given.
+    ///
+    /// Defaults to [`SeqStyle::Repeated`]. Fields using one of the
+    /// [`seq_style`] modules override it. Keys such as `tags[]` and
+    /// `tags[0]` are accepted for a struct field `tags` regardless of the
+    /// style, and for maps only with `SeqStyle::Bracketed` and
+    /// `SeqStyle::Indexed`. Elements are ordered by their index if they have
+    /// one. If [`Options::nesting`] is set, such keys are parsed as nested
+    /// keys instead, which [`Nesting::Dot`] does not support.
+    ///
+    /// [`seq_style`]: crate::seq_style
+    pub fn seq_style(mut self, style: SeqStyle) -> Self {
+        self.values.seq_style = style;
+        self
+    }
+
+    /// Whether an empty value, e.g. `cursor=`, is `None` for an `Option`
+    /// field instead of `Some` of an empty value.
+    ///
+    /// Defaults to `false`, where `cursor=` is `Some("")` and `limit=` fails
+    /// to parse as `Option<u32>`. Takes precedence over
+    /// [`Options::bare_keys_as_true`] for `Option<bool>` fields.
+    pub fn empty_as_none(mut self, empty_as_none: bool) -> Self {
+        self.values.empty_as_none = empty_as_none;
+        self
+    }
+
+    /// Whether an empty value is `true` for a `bool` field, so that a bare
+    /// key such as `includePins` in `?includePins&limit=5` acts as a flag.
+    ///
+    /// Defaults to `false`. Parsing does not distinguish a bare key from one
+    /// with an empty value, so `includePins=` is `true` as well.
+    pub fn bare_keys_as_true(mut self, bare_keys_as_true: bool) -> Self {
+        self.values.bare_keys_as_true = bare_keys_as_true;
+        self
+    }
+
+    /// Whether numbers are parsed following the atproto data model, instead
+    /// of with their `FromStr` impls.
+    ///
+    /// Defaults to `false`. When enabled, integers must be written without a
+    /// `+` sign or leading zeros and fit in an `i64`, so `+5`, `007` and `-0`
+    /// are rejected, and floats are rejected entirely.
+    pub fn strict_scalars(mut self, strict: bool) -> Self {
+        self.values.strict_scalars = strict;
+        self
+    }
+
+    /// Whether booleans may also be given as `1`, `0`, `yes` and `no`, in
+    /// any case, e.g. `TRUE`.
+    ///
+    /// Defaults to `false`, where only `true` and `false` are accepted.
+    pub fn lenient_bools(mut self, lenient: bool) -> Self {
+        self.values.lenient_bools = lenient;
+        self
+    }
+
+    /// The maximum length of the input, in bytes.
+    ///
+    /// Only enforced by the `from_*` methods, which check it before parsing.
+    /// `from_reader` stops reading once the limit is exceeded.
+    pub fn max_input_len(mut self, max: usize) -> Self {
+        self.max_input_len = Some(max);
+        self
+    }
+
+    /// The maximum number of key-value pairs in the input.
+    pub fn max_pairs(mut self, max: usize) -> Self {
+        self.max_pairs = Some(max);
+        self
+    }
+
+    /// The maximum length of a decoded key, in bytes.
+    pub fn max_key_len(mut self, max: usize) -> Self {
+        self.max_key_len = Some(max);
+        self
+    }
+
+    /// The maximum length of a decoded value, in bytes.
+    pub fn max_value_len(mut self, max: usize) -> Self {
+        self.max_value_len = Some(max);
+        self
+    }
+
+    /// The maximum number of values given for a single key of a struct or
+    /// map.
+    pub fn max_values_per_key(mut self, max: usize) -> Self {
+        self.max_values_per_key = Some(max);
+        self
+    }
+
+    /// Whether malformed percent-encoded sequences (e.g. `%zz`) and invalid
+    /// UTF-8 are errors, instead of being passed through literally and
+    /// replaced with U+FFFD respectively.
+    ///
+    /// Defaults to `false`. Errors report the byte offset of the offending
+    /// sequence. Only enforced by the `from_*` methods, which decode the
+    /// input themselves.
+    pub fn strict_decoding(mut self, strict: bool) -> Self {
+        self.strict_decoding = strict;
+        self
+    }
+
+    /// Whether `+` is decoded as a space, as in
+    /// `application/x-www-form-urlencoded`, or kept literally, as in RFC 3986
+    /// query strings.
+    ///
+    /// Defaults to `true`. Only enforced by the `from_*` methods, which decode
+    /// the input themselves.
+    pub fn plus_as_space(mut self, plus_as_space: bool) -> Self {
+        self.plus_as_space = plus_as_space;
+        self
+    }
+
+    /// Whether keys such as `filter.author` or `filter[author]` are
+    /// deserialized into nested structs, maps and sequences of structs, and
+    /// with which notation.
+    ///
+    /// Defaults to `None`, where such keys are taken literally. Keys that are
+    /// not well-formed for the notation are also taken literally. Sequences
+    /// of structs can be given with indices, as in `items.0.name` and
+    /// `items[0][name]`, which may be sparse and in any order, or as
+    /// `items[][name]`.
+    pub fn nesting(mut self, nesting: Option<Nesting>) -> Self {
+        self.nesting = nesting;
+        self
+    }
+
+    /// The maximum number of segments of a nested key, e.g. 3 for
+    /// `items[0][name]`.
+    ///
+    /// Defaults to 16. Only applies when [`nesting`] is set.
+    ///
+    /// [`nesting`]: Options::nesting
+    pub fn max_depth(mut self, max: usize) -> Self {
+        self.max_depth = max;
+        self
+    }
+
+    /// Returns a new `Deserializer` using these options.
+    pub fn deserializer<'de>(
+        &self,
+        parser: UrlEncodedParse<'de>,
+    ) -> Deserializer<'de> {
+        Deserializer::with_options(parser, self.clone())
+    }
+
+    /// Deserializes a `application/x-www-form-urlencoded` value from a
+    /// `&[u8]` using these options.
+    pub fn from_bytes<'de, T>(&self, input: &'de [u8]) -> Result<T, Error>
+    where
+        T: de::Deserialize<'de>,
+    {
+        self.check_input_len(input.len())?;
+        T::deserialize(Deserializer::from_bytes_with_options(
+            input,
+            self.clone(),
+        ))
+    }
+
+    /// Deserializes a `application/x-www-form-urlencoded` value from a `&str`
+    /// using these options.
+    pub fn from_str<'de, T>(&self, input: &'de str) -> Result<T, Error>
+    where
+        T: de::Deserialize<'de>,
+    {
+        self.from_bytes(input.as_bytes())
+    }
+
+    /// Reads all bytes from `reader` and deserializes them with `from_bytes`.
+    pub fn from_reader<T, R>(&self, reader: R) -> Result<T, Error>
+    where
+        T: de::DeserializeOwned,
+        R: Read,
+    {
+        // Reading one byte past the limit is enough to detect that it is
+        // exceeded.
+        let max = self.max_input_len.map_or(u64::MAX, |max| max as u64 + 1);
+        let mut buf = vec![];
+        reader.take(max).read_to_end(&mut buf).map_err(|e| {
+            Error::custom(format_args!("could not read input: {}", e))
+        })?;
+        self.from_bytes(&buf)
+    }
+
+    fn check_input_len(&self, len: usize) -> Result<(), Error> {
+        match self.max_input_len {
+            Some(max) if len > max => {
+                let msg = format_args!("input is longer than {} bytes", max);
+                Err(Error::new(
+                    ErrorKind::LimitExceeded(Limit::InputLength),
+                    msg,
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+}
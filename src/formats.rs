@@ -0,0 +1,690 @@
+//! Validated newtypes for the atproto lexicon string formats.
+//!
+//! Each type can only hold a value that is valid for its format. They
+//! serialize as plain strings, and fail to deserialize, with an error naming
+//! the format, when given a malformed value.
+//!
+//! ```
+//! use serde_derive::{Deserialize, Serialize};
+//! use serde_urlencoded_xrpc::formats::{AtIdentifier, Nsid};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Params {
+//!     repo: AtIdentifier,
+//!     collection: Nsid,
+//! }
+//!
+//! let params: Params = serde_urlencoded_xrpc::from_str(
+//!     "repo=alice.bsky.social&collection=app.bsky.feed.post",
+//! )
+//! .unwrap();
+//! assert_eq!(params.collection.as_str(), "app.bsky.feed.post");
+//!
+//! assert!(serde_urlencoded_xrpc::from_str::<Params>(
+//!     "repo=alice&collection=app.bsky.feed.post",
+//! )
+//! .is_err());
+//! ```
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// Error returned when a string is not valid for a format.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    format: &'static str,
+    reason: &'static str,
+}
+
+impl Error {
+    fn new(format: &'static str, reason: &'static str) -> Self {
+        Error { format, reason }
+    }
+
+    /// Returns the name of the format, e.g. `"did"`.
+    pub fn format(&self) -> &'static str {
+        self.format
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {}: {}", self.format, self.reason)
+    }
+}
+
+impl error::Error for Error {}
+
+macro_rules! string_format {
+    ($(#[$attr:meta])* $name:ident, $format:literal, $validate:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(String);
+
+        impl $name {
+            /// The name of this format in lexicon schemas.
+            pub const FORMAT: &'static str = $format;
+
+            /// Validates `value` and wraps it.
+            pub fn new<S: Into<String>>(value: S) -> Result<Self, Error> {
+                let value = value.into();
+                match $validate(&value) {
+                    Ok(()) => Ok($name(value)),
+                    Err(reason) => Err(Error::new($format, reason)),
+                }
+            }
+
+            /// Returns the value as a string slice.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            /// Returns the wrapped string.
+            pub fn into_string(self) -> String {
+                self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = Error;
+
+            fn from_str(s: &str) -> Result<Self, Error> {
+                $name::new(s)
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = Error;
+
+            fn try_from(s: String) -> Result<Self, Error> {
+                $name::new(s)
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = Error;
+
+            fn try_from(s: &str) -> Result<Self, Error> {
+                $name::new(s)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> String {
+                value.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                deserializer.deserialize_str(FormatVisitor::new($format))
+            }
+        }
+    };
+}
+
+/// Deserializes any of the formats from a string.
+struct FormatVisitor<T> {
+    format: &'static str,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T> FormatVisitor<T> {
+    fn new(format: &'static str) -> Self {
+        FormatVisitor {
+            format,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'de, T> Visitor<'de> for FormatVisitor<T>
+where
+    T: FromStr<Err = Error> + TryFrom<String, Error = Error>,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a string in {} format", self.format)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<T, E> {
+        T::try_from(v).map_err(E::custom)
+    }
+}
+
+string_format! {
+    /// A decentralized identifier, e.g. `did:plc:7iza6de2dwap2sbkpav7c6c6`.
+    Did, "did", validate_did
+}
+
+string_format! {
+    /// A handle, e.g. `alice.bsky.social`.
+    ///
+    /// Handles are case-insensitive, but are kept as given.
+    Handle, "handle", validate_handle
+}
+
+string_format! {
+    /// A namespaced identifier, e.g. `app.bsky.feed.getPosts`.
+    Nsid, "nsid", validate_nsid
+}
+
+string_format! {
+    /// An AT URI, e.g. `at://did:plc:xyz/app.bsky.feed.post/3k2a`.
+    ///
+    /// The authority must be a valid DID or handle, the collection a valid
+    /// NSID and the record key a valid record key.
+    AtUri, "at-uri", validate_at_uri
+}
+
+string_format! {
+    /// A content identifier in its string encoding.
+    ///
+    /// As in the reference implementation, only the character set and length
+    /// are checked, not that the CID can be decoded.
+    Cid, "cid", validate_cid
+}
+
+string_format! {
+    /// A timestamp identifier, e.g. `3jzfcijpj2z2a`.
+    Tid, "tid", validate_tid
+}
+
+string_format! {
+    /// A record key, e.g. `self` or a TID.
+    RecordKey, "record-key", validate_record_key
+}
+
+string_format! {
+    /// An RFC 3339 datetime with a timezone, e.g.
+    /// `1985-04-12T23:20:50.123Z`.
+    Datetime, "datetime", validate_datetime
+}
+
+string_format! {
+    /// A BCP 47 language tag, e.g. `pt-BR`.
+    Language, "language", validate_language
+}
+
+/// Either a DID or a handle, as accepted by the `at-identifier` format.
+///
+/// Like the other formats, it compares, orders and hashes as its string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AtIdentifier {
+    Did(Did),
+    Handle(Handle),
+}
+
+impl AtIdentifier {
+    /// The name of this format in lexicon schemas.
+    pub const FORMAT: &'static str = "at-identifier";
+
+    /// Validates `value` and wraps it.
+    pub fn new<S: Into<String>>(value: S) -> Result<Self, Error> {
+        let value = value.into();
+        if value.starts_with("did:") {
+            Did::new(value).map(AtIdentifier::Did)
+        } else {
+            Handle::new(value).map(AtIdentifier::Handle)
+        }
+        .map_err(|err| Error::new(Self::FORMAT, err.reason))
+    }
+
+    /// Returns the value as a string slice.
+    pub fn as_str(&self) -> &str {
+        match self {
+            AtIdentifier::Did(did) => did.as_str(),
+            AtIdentifier::Handle(handle) => handle.as_str(),
+        }
+    }
+}
+
+impl FromStr for AtIdentifier {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        AtIdentifier::new(s)
+    }
+}
+
+impl TryFrom<String> for AtIdentifier {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Error> {
+        AtIdentifier::new(s)
+    }
+}
+
+impl TryFrom<&str> for AtIdentifier {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Error> {
+        AtIdentifier::new(s)
+    }
+}
+
+impl From<Did> for AtIdentifier {
+    fn from(did: Did) -> Self {
+        AtIdentifier::Did(did)
+    }
+}
+
+impl From<Handle> for AtIdentifier {
+    fn from(handle: Handle) -> Self {
+        AtIdentifier::Handle(handle)
+    }
+}
+
+impl Deref for AtIdentifier {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for AtIdentifier {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for AtIdentifier {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialOrd for AtIdentifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AtIdentifier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for AtIdentifier {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Display for AtIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl Serialize for AtIdentifier {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for AtIdentifier {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FormatVisitor::new(Self::FORMAT))
+    }
+}
+
+type Validation = Result<(), &'static str>;
+
+/// Validates `value` against the lexicon string format named `format`.
+///
+/// Returns `None` for formats without a type in this module, such as `uri`.
+#[cfg_attr(not(feature = "lexicon"), allow(dead_code))]
+pub(crate) fn validate(format: &str, value: &str) -> Option<Result<(), Error>> {
+    let (format, validate): (_, fn(&str) -> Validation) = match format {
+        Did::FORMAT => (Did::FORMAT, validate_did),
+        Handle::FORMAT => (Handle::FORMAT, validate_handle),
+        Nsid::FORMAT => (Nsid::FORMAT, validate_nsid),
+        AtUri::FORMAT => (AtUri::FORMAT, validate_at_uri),
+        Cid::FORMAT => (Cid::FORMAT, validate_cid),
+        Tid::FORMAT => (Tid::FORMAT, validate_tid),
+        RecordKey::FORMAT => (RecordKey::FORMAT, validate_record_key),
+        Datetime::FORMAT => (Datetime::FORMAT, validate_datetime),
+        Language::FORMAT => (Language::FORMAT, validate_language),
+        AtIdentifier::FORMAT => {
+            return Some(AtIdentifier::new(value).map(drop));
+        }
+        _ => return None,
+    };
+    Some(validate(value).map_err(|reason| Error::new(format, reason)))
+}
+
+fn validate_did(value: &str) -> Validation {
+    if value.len() > 2048 {
+        return Err("longer than 2048 characters");
+    }
+    let rest = value.strip_prefix("did:").ok_or("must start with `did:`")?;
+    let (method, identifier) = rest
+        .split_once(':')
+        .ok_or("missing method-specific identifier")?;
+    if method.is_empty() || !method.bytes().all(|b| b.is_ascii_lowercase()) {
+        return Err("method must be lowercase ASCII letters");
+    }
+    if !identifier.bytes().all(|b| {
+        b.is_ascii_alphanumeric()
+            || matches!(b, b'.' | b'_' | b':' | b'%' | b'-')
+    }) {
+        return Err("identifier contains a disallowed character");
+    }
+    match identifier.bytes().last() {
+        None => Err("missing method-specific identifier"),
+        Some(b':') | Some(b'%') => Err("must not end with `:` or `%`"),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Validates a DNS label made of ASCII alphanumerics and inner hyphens.
+fn validate_label(label: &str) -> Validation {
+    if label.is_empty() || label.len() > 63 {
+        return Err("segment must be 1 to 63 characters long");
+    }
+    if !label
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    {
+        return Err("segment contains a disallowed character");
+    }
+    if label.starts_with('-') || label.ends_with('-') {
+        return Err("segment must not start or end with a hyphen");
+    }
+    Ok(())
+}
+
+fn validate_handle(value: &str) -> Validation {
+    if value.len() > 253 {
+        return Err("longer than 253 characters");
+    }
+    let labels: Vec<_> = value.split('.').collect();
+    if labels.len() < 2 {
+        return Err("must have at least two segments");
+    }
+    for label in &labels {
+        validate_label(label)?;
+    }
+    let tld = labels[labels.len() - 1];
+    if !tld.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err("last segment must start with a letter");
+    }
+    Ok(())
+}
+
+fn validate_nsid(value: &str) -> Validation {
+    if value.len() > 317 {
+        return Err("longer than 317 characters");
+    }
+    let segments: Vec<_> = value.split('.').collect();
+    if segments.len() < 3 {
+        return Err("must have at least three segments");
+    }
+    let (name, authority) = segments.split_last().unwrap();
+    if authority.iter().map(|s| s.len() + 1).sum::<usize>() - 1 > 253 {
+        return Err("domain authority longer than 253 characters");
+    }
+    for segment in authority {
+        validate_label(segment)?;
+    }
+    if !authority[0].starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err("first segment must start with a letter");
+    }
+    if name.is_empty() || name.len() > 63 {
+        return Err("name must be 1 to 63 characters long");
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !name.bytes().all(|b| b.is_ascii_alphanumeric())
+    {
+        return Err("name must be ASCII alphanumerics starting with a letter");
+    }
+    Ok(())
+}
+
+fn validate_at_uri(value: &str) -> Validation {
+    if value.len() > 8 * 1024 {
+        return Err("longer than 8 KiB");
+    }
+    let rest = value
+        .strip_prefix("at://")
+        .ok_or("must start with `at://`")?;
+    let (path, fragment) = match rest.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (rest, None),
+    };
+    if let Some(fragment) = fragment {
+        if !fragment.starts_with('/')
+            || fragment
+                .bytes()
+                .any(|b| b.is_ascii_whitespace() || b == b'#')
+        {
+            return Err("fragment must start with `/`");
+        }
+    }
+
+    let mut segments = path.split('/');
+    let authority = segments.next().unwrap();
+    if authority.starts_with("did:") {
+        validate_did(authority)
+    } else {
+        validate_handle(authority)
+    }
+    .map_err(|_| "authority must be a valid DID or handle")?;
+    if let Some(collection) = segments.next() {
+        validate_nsid(collection)
+            .map_err(|_| "collection must be a valid NSID")?;
+    }
+    if let Some(record_key) = segments.next() {
+        validate_record_key(record_key)
+            .map_err(|_| "record key must be a valid record key")?;
+    }
+    if segments.next().is_some() {
+        return Err("path must have at most two segments");
+    }
+    Ok(())
+}
+
+fn validate_cid(value: &str) -> Validation {
+    if value.len() < 8 || value.len() > 256 {
+        return Err("must be 8 to 256 characters long");
+    }
+    if !value
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'=')
+    {
+        return Err("contains a disallowed character");
+    }
+    Ok(())
+}
+
+fn validate_tid(value: &str) -> Validation {
+    const ALPHABET: &[u8] = b"234567abcdefghijklmnopqrstuvwxyz";
+
+    if value.len() != 13 {
+        return Err("must be 13 characters long");
+    }
+    if !value.bytes().all(|b| ALPHABET.contains(&b)) {
+        return Err("must use the base32-sortable alphabet");
+    }
+    if !value
+        .starts_with(|c| ('2'..='7').contains(&c) || ('a'..='j').contains(&c))
+    {
+        return Err("high bit must not be set");
+    }
+    Ok(())
+}
+
+fn validate_record_key(value: &str) -> Validation {
+    if value.is_empty() || value.len() > 512 {
+        return Err("must be 1 to 512 characters long");
+    }
+    if value == "." || value == ".." {
+        return Err("must not be `.` or `..`");
+    }
+    if !value.bytes().all(|b| {
+        b.is_ascii_alphanumeric()
+            || matches!(b, b'_' | b'~' | b'.' | b':' | b'-')
+    }) {
+        return Err("contains a disallowed character");
+    }
+    Ok(())
+}
+
+fn validate_datetime(value: &str) -> Validation {
+    const INVALID: &str = "must be an RFC 3339 datetime with a timezone";
+
+    fn number(digits: &[u8]) -> Option<u32> {
+        digits.iter().try_fold(0, |acc, &digit| {
+            if digit.is_ascii_digit() {
+                Some(acc * 10 + u32::from(digit - b'0'))
+            } else {
+                None
+            }
+        })
+    }
+
+    if value.len() > 64 {
+        return Err("longer than 64 characters");
+    }
+    let bytes = value.as_bytes();
+    if bytes.len() < 20
+        || bytes[4] != b'-'
+        || bytes[7] != b'-'
+        || bytes[10] != b'T'
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return Err(INVALID);
+    }
+    let field = |range: std::ops::Range<usize>| number(&bytes[range]);
+    let (year, month, day) = (field(0..4), field(5..7), field(8..10));
+    let (hour, minute, second) = (field(11..13), field(14..16), field(17..19));
+    let (year, month, day, hour, minute, second) =
+        match (year, month, day, hour, minute, second) {
+            (Some(y), Some(mo), Some(d), Some(h), Some(mi), Some(s)) => {
+                (y, mo, d, h, mi, s)
+            }
+            _ => return Err(INVALID),
+        };
+    if year == 0 {
+        return Err("year must not be 0000");
+    }
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return Err("month out of range"),
+    };
+    if day == 0
+        || day > days_in_month
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err("date or time out of range");
+    }
+
+    let mut rest = &bytes[19..];
+    if let Some(fraction) = rest.strip_prefix(b".") {
+        let digits = fraction.iter().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 || digits > 20 {
+            return Err(INVALID);
+        }
+        rest = &fraction[digits..];
+    }
+    match rest {
+        b"Z" => Ok(()),
+        [b'-', b'0', b'0', b':', b'0', b'0'] => {
+            Err("timezone must not be `-00:00`")
+        }
+        [b'+' | b'-', h1, h2, b':', m1, m2] => {
+            match (number(&[*h1, *h2]), number(&[*m1, *m2])) {
+                (Some(h), Some(m)) if h <= 23 && m <= 59 => Ok(()),
+                _ => Err(INVALID),
+            }
+        }
+        _ => Err(INVALID),
+    }
+}
+
+fn validate_language(value: &str) -> Validation {
+    const INVALID: &str = "must be a BCP 47 language tag";
+
+    let mut subtags = value.split('-');
+    let primary = subtags.next().unwrap();
+    if primary != "i"
+        && !((2..=3).contains(&primary.len())
+            && primary.bytes().all(|b| b.is_ascii_lowercase()))
+    {
+        return Err(INVALID);
+    }
+    for subtag in subtags {
+        if subtag.is_empty()
+            || subtag.len() > 8
+            || !subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+        {
+            return Err(INVALID);
+        }
+    }
+    Ok(())
+}
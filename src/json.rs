@@ -0,0 +1,81 @@
+//! Query parameters holding a JSON value.
+
+use serde::de::{Deserialize, DeserializeOwned, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::ops::{Deref, DerefMut};
+
+/// A value encoded as compact JSON in a single query parameter.
+///
+/// The JSON text is percent-encoded like any other string. Malformed JSON,
+/// or JSON that does not match `T`, fails to deserialize with an error
+/// naming the field. Use [`with::json`](crate::with::json) instead to keep
+/// the field's own type.
+///
+/// ```
+/// use serde_derive::{Deserialize, Serialize};
+/// use serde_urlencoded_xrpc::json::Json;
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Range {
+///     start: u32,
+///     end: u32,
+/// }
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Params {
+///     range: Json<Range>,
+/// }
+///
+/// let params = Params {
+///     range: Json(Range { start: 1, end: 5 }),
+/// };
+/// let query = "range=%7B%22start%22%3A1%2C%22end%22%3A5%7D";
+/// assert_eq!(serde_urlencoded_xrpc::to_string(&params), Ok(query.to_owned()));
+/// assert_eq!(serde_urlencoded_xrpc::from_str(query), Ok(params));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Json<T>(pub T);
+
+impl<T> Json<T> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Json<T> {
+    fn from(value: T) -> Self {
+        Json(value)
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Json<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Serialize> Serialize for Json<T> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        crate::with::json::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Json<T> {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        crate::with::json::deserialize(deserializer).map(Json)
+    }
+}
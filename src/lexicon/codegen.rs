@@ -0,0 +1,351 @@
+//! Generation of Rust params structs from lexicon documents.
+//!
+//! Each XRPC method with parameters becomes a struct named after the last
+//! segment of its NSID, in nested modules named after the other segments,
+//! e.g. `app::bsky::feed::GetAuthorFeedParams`. Fields are renamed to
+//! `camelCase`, parameters that are not required are `Option`s, arrays are
+//! `Vec`s, empty by default unless required, and strings with a known
+//! `format` use the newtypes of [`formats`](crate::formats).
+//!
+//! From `build.rs`:
+//!
+//! ```no_run
+//! use serde_urlencoded_xrpc::lexicon::codegen::Options;
+//! use std::env;
+//! use std::path::Path;
+//!
+//! let out = Path::new(&env::var("OUT_DIR").unwrap()).join("params.rs");
+//! Options::new().generate_dir("lexicons", out).unwrap();
+//! println!("cargo:rerun-if-changed=lexicons");
+//! ```
+//!
+//! Then in the crate:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/params.rs"));
+//! ```
+
+use crate::formats::{
+    AtIdentifier, AtUri, Cid, Datetime, Did, Handle, Language, Nsid, RecordKey,
+    Tid,
+};
+use crate::lexicon::{Error, Lexicons, Method, PropertyType};
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Options for generating params structs.
+#[derive(Clone, Debug)]
+pub struct Options {
+    crate_path: String,
+    serde_path: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            crate_path: "::serde_urlencoded_xrpc".to_owned(),
+            serde_path: "::serde".to_owned(),
+        }
+    }
+}
+
+impl Options {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// Sets the path of this crate in the generated code, by default
+    /// `::serde_urlencoded_xrpc`.
+    pub fn crate_path(mut self, path: impl Into<String>) -> Self {
+        self.crate_path = path.into();
+        self
+    }
+
+    /// Sets the path of the `Serialize` and `Deserialize` derive macros in
+    /// the generated code, by default `::serde`, which requires the `derive`
+    /// feature of `serde`.
+    pub fn serde_path(mut self, path: impl Into<String>) -> Self {
+        self.serde_path = path.into();
+        self
+    }
+
+    /// Generates the params structs of every method with parameters.
+    pub fn generate(&self, lexicons: &Lexicons) -> String {
+        let mut root = Module::default();
+        for method in lexicons.iter() {
+            if method.parameters.properties.is_empty() {
+                continue;
+            }
+            let mut segments: Vec<_> = method.id.split('.').collect();
+            let name = segments.pop().unwrap_or_default();
+            let module =
+                segments.into_iter().fold(&mut root, |module, segment| {
+                    module.modules.entry(segment.to_owned()).or_default()
+                });
+            module.structs.push((name.to_owned(), method));
+        }
+
+        let mut out = String::from(
+            "// Generated from lexicon documents by serde_urlencoded_xrpc. Do \
+             not edit.\n",
+        );
+        self.write_module(&mut out, &root, 0);
+        out
+    }
+
+    /// Generates the params structs of the lexicon documents in `dir`, as
+    /// loaded by [`Lexicons::load_dir`], and writes them to `out`.
+    pub fn generate_dir(
+        &self,
+        dir: impl AsRef<Path>,
+        out: impl AsRef<Path>,
+    ) -> Result<(), Error> {
+        let code = self.generate(&Lexicons::load_dir(dir)?);
+        let out = out.as_ref();
+        fs::write(out, code).map_err(|err| Error::io(out, err))
+    }
+
+    fn write_module(
+        &self,
+        out: &mut String,
+        module: &Module<'_>,
+        depth: usize,
+    ) {
+        let indent = "    ".repeat(depth);
+        let mut first = depth > 0;
+        for (name, method) in &module.structs {
+            if !std::mem::take(&mut first) {
+                out.push('\n');
+            }
+            self.write_struct(out, name, method, &indent);
+        }
+        for (name, child) in &module.modules {
+            if !std::mem::take(&mut first) {
+                out.push('\n');
+            }
+            let _ =
+                writeln!(out, "{}pub mod {} {{", indent, module_ident(name));
+            self.write_module(out, child, depth + 1);
+            let _ = writeln!(out, "{}}}", indent);
+        }
+    }
+
+    fn write_struct(
+        &self,
+        out: &mut String,
+        name: &str,
+        method: &Method,
+        indent: &str,
+    ) {
+        let kind = match method.kind {
+            super::MethodKind::Query => "query",
+            super::MethodKind::Procedure => "procedure",
+            super::MethodKind::Subscription => "subscription",
+        };
+        let _ = writeln!(
+            out,
+            "{}/// Parameters of the `{}` {}.",
+            indent, method.id, kind,
+        );
+        if let Some(description) = &method.description {
+            write_doc(out, indent, description);
+        }
+        let _ = writeln!(out, "{}#[derive(Clone, Debug, PartialEq)]", indent);
+        let _ = writeln!(
+            out,
+            "{0}#[derive({1}::Serialize, {1}::Deserialize)]",
+            indent, self.serde_path,
+        );
+        let mut chars = name.chars();
+        let first = chars.next().map(|c| c.to_ascii_uppercase());
+        let _ = writeln!(
+            out,
+            "{}pub struct {}{}Params {{",
+            indent,
+            first.into_iter().collect::<String>(),
+            chars.as_str(),
+        );
+
+        for (i, (name, property)) in
+            method.parameters.properties.iter().enumerate()
+        {
+            let indent = format!("{}    ", indent);
+            let documented = property.description.is_some()
+                || default(&property.ty).is_some();
+            if i > 0 && documented {
+                out.push('\n');
+            }
+            if let Some(description) = &property.description {
+                let _ =
+                    writeln!(out, "{}/// {}", indent, first_line(description));
+                write_doc(out, &indent, rest_lines(description));
+            }
+            if let Some(default) = default(&property.ty) {
+                if property.description.is_some() {
+                    let _ = writeln!(out, "{}///", indent);
+                }
+                let _ =
+                    writeln!(out, "{}/// Defaults to `{}`.", indent, default);
+            }
+
+            let field = snake_case(name);
+            let mut attrs = vec![];
+            if field != *name {
+                attrs.push(format!("rename = \"{}\"", name));
+            }
+            let mut ty = self.rust_type(&property.ty);
+            let required = method.parameters.is_required(name);
+            match property.ty {
+                // An empty array is serialized as no pairs at all, which only
+                // a required one rejects.
+                PropertyType::Array { .. } if !required => {
+                    attrs.push("default".to_owned())
+                }
+                PropertyType::Array { .. } => {}
+                _ if !required => ty = format!("Option<{}>", ty),
+                _ => {}
+            }
+            if !attrs.is_empty() {
+                let _ =
+                    writeln!(out, "{}#[serde({})]", indent, attrs.join(", "));
+            }
+            let _ =
+                writeln!(out, "{}pub {}: {},", indent, field_ident(&field), ty);
+        }
+        let _ = writeln!(out, "{}}}", indent);
+    }
+
+    fn rust_type(&self, ty: &PropertyType) -> String {
+        match ty {
+            PropertyType::Boolean { .. } => "bool".to_owned(),
+            PropertyType::Integer { .. } => "i64".to_owned(),
+            PropertyType::String { format, .. } => {
+                match format.as_deref().and_then(format_type) {
+                    Some(name) => {
+                        format!("{}::formats::{}", self.crate_path, name)
+                    }
+                    None => "String".to_owned(),
+                }
+            }
+            PropertyType::Array { items, .. } => {
+                format!("Vec<{}>", self.rust_type(items))
+            }
+            PropertyType::Unknown => "String".to_owned(),
+        }
+    }
+}
+
+/// Generates the params structs of `lexicons` with the default options.
+pub fn generate(lexicons: &Lexicons) -> String {
+    Options::new().generate(lexicons)
+}
+
+#[derive(Default)]
+struct Module<'a> {
+    structs: Vec<(String, &'a Method)>,
+    modules: BTreeMap<String, Module<'a>>,
+}
+
+/// Returns the name of the newtype of a string format, if any.
+fn format_type(format: &str) -> Option<&'static str> {
+    Some(match format {
+        Did::FORMAT => "Did",
+        Handle::FORMAT => "Handle",
+        AtIdentifier::FORMAT => "AtIdentifier",
+        Nsid::FORMAT => "Nsid",
+        AtUri::FORMAT => "AtUri",
+        Cid::FORMAT => "Cid",
+        Tid::FORMAT => "Tid",
+        RecordKey::FORMAT => "RecordKey",
+        Datetime::FORMAT => "Datetime",
+        Language::FORMAT => "Language",
+        _ => return None,
+    })
+}
+
+fn default(ty: &PropertyType) -> Option<String> {
+    match ty {
+        PropertyType::Boolean { default } => default.map(|v| v.to_string()),
+        PropertyType::Integer { default, .. } => default.map(|v| v.to_string()),
+        PropertyType::String { default, .. } => default.clone(),
+        _ => None,
+    }
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default().trim_end()
+}
+
+fn rest_lines(text: &str) -> &str {
+    text.split_once('\n').map_or("", |(_, rest)| rest)
+}
+
+/// Writes `text` as doc comment lines, after a blank line if not empty.
+fn write_doc(out: &mut String, indent: &str, text: &str) {
+    if text.trim().is_empty() {
+        return;
+    }
+    let _ = writeln!(out, "{}///", indent);
+    for line in text.trim().lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            let _ = writeln!(out, "{}///", indent);
+        } else {
+            let _ = writeln!(out, "{}/// {}", indent, line);
+        }
+    }
+}
+
+/// Converts a `camelCase` name to `snake_case`, keeping acronyms together,
+/// e.g. `getDIDDoc` to `get_did_doc`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower =
+                matches!(chars.get(i + 1), Some(c) if c.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_lower)
+            {
+                out.push('_');
+            }
+        }
+        out.push(if c == '-' {
+            '_'
+        } else {
+            c.to_ascii_lowercase()
+        });
+    }
+    out
+}
+
+fn module_ident(segment: &str) -> String {
+    field_ident(&segment.replace('-', "_").to_ascii_lowercase())
+}
+
+/// Escapes `name` if it is a Rust keyword.
+fn field_ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const",
+        "continue", "do", "dyn", "else", "enum", "extern", "false", "final",
+        "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match",
+        "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+        "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+        "unsized", "use", "virtual", "where", "while", "yield",
+    ];
+    match name {
+        "crate" | "self" | "super" => format!("{}_", name),
+        name if KEYWORDS.contains(&name) => format!("r#{}", name),
+        name if name.starts_with(|c: char| c.is_ascii_digit()) => {
+            format!("_{}", name)
+        }
+        name => name.to_owned(),
+    }
+}
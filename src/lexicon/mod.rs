@@ -0,0 +1,203 @@
+//! Validation of query parameters against lexicon documents, for methods
+//! without a Rust type, and generation of params structs for those with one.
+//!
+//! ```
+//! use serde_urlencoded_xrpc::lexicon::{Lexicons, ViolationKind};
+//! use serde_urlencoded_xrpc::query_value::QueryValue;
+//!
+//! let mut lexicons = Lexicons::new();
+//! lexicons
+//!     .add_json(
+//!         r#"{
+//!             "lexicon": 1,
+//!             "id": "app.bsky.feed.getAuthorFeed",
+//!             "defs": {
+//!                 "main": {
+//!                     "type": "query",
+//!                     "parameters": {
+//!                         "type": "params",
+//!                         "required": ["actor"],
+//!                         "properties": {
+//!                             "actor": {
+//!                                 "type": "string",
+//!                                 "format": "at-identifier"
+//!                             },
+//!                             "limit": {
+//!                                 "type": "integer",
+//!                                 "minimum": 1,
+//!                                 "maximum": 100,
+//!                                 "default": 50
+//!                             }
+//!                         }
+//!                     }
+//!                 }
+//!             }
+//!         }"#,
+//!     )
+//!     .unwrap();
+//!
+//! let method = lexicons.get("app.bsky.feed.getAuthorFeed").unwrap();
+//! let params = method
+//!     .parameters
+//!     .validate("actor=alice.bsky.social&limit=10")
+//!     .unwrap();
+//! assert_eq!(params["limit"], QueryValue::Integer(10));
+//!
+//! let params = method.parameters.validate("actor=alice.test").unwrap();
+//! assert_eq!(params["limit"], QueryValue::Integer(50));
+//!
+//! let violations = method.parameters.validate("limit=500").unwrap_err();
+//! assert_eq!(violations[0].kind(), ViolationKind::Missing);
+//! assert_eq!(
+//!     violations[1].to_string(),
+//!     "parameter `limit`: 500 is greater than the maximum 100",
+//! );
+//! ```
+
+pub mod codegen;
+mod schema;
+mod validate;
+
+pub use self::schema::{
+    Method, MethodKind, Parameters, Property, PropertyType,
+};
+pub use self::validate::{Violation, ViolationKind};
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The XRPC methods of a set of lexicon documents, by NSID.
+#[derive(Clone, Debug, Default)]
+pub struct Lexicons {
+    methods: BTreeMap<String, Method>,
+}
+
+impl Lexicons {
+    /// Returns an empty set of lexicons.
+    pub fn new() -> Self {
+        Lexicons::default()
+    }
+
+    /// Loads every `.json` file in `dir` and its subdirectories.
+    ///
+    /// Documents whose main definition is not a query, procedure or
+    /// subscription, e.g. records, are skipped.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let mut lexicons = Lexicons::new();
+        let mut dirs = vec![dir.as_ref().to_owned()];
+        while let Some(dir) = dirs.pop() {
+            let entries =
+                fs::read_dir(&dir).map_err(|err| Error::io(&dir, err))?;
+            for entry in entries {
+                let path = entry.map_err(|err| Error::io(&dir, err))?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension() == Some("json".as_ref()) {
+                    lexicons.add_file(&path)?;
+                }
+            }
+        }
+        Ok(lexicons)
+    }
+
+    /// Loads a single lexicon document from a file.
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let json =
+            fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+        self.add_json(&json).map_err(|err| err.with_path(path))
+    }
+
+    /// Loads a single lexicon document.
+    pub fn add_json(&mut self, json: &str) -> Result<(), Error> {
+        let doc: Value = serde_json::from_str(json).map_err(ErrorKind::Json)?;
+        if let Some(method) = Method::parse(&doc).map_err(ErrorKind::Schema)? {
+            self.methods.insert(method.id.clone(), method);
+        }
+        Ok(())
+    }
+
+    /// Returns the method with the given NSID, if any.
+    pub fn get(&self, nsid: &str) -> Option<&Method> {
+        self.methods.get(nsid)
+    }
+
+    /// Returns every method, ordered by NSID.
+    pub fn iter(&self) -> impl Iterator<Item = &Method> {
+        self.methods.values()
+    }
+
+    /// Returns the number of methods.
+    pub fn len(&self) -> usize {
+        self.methods.len()
+    }
+
+    /// Returns whether there are no methods.
+    pub fn is_empty(&self) -> bool {
+        self.methods.is_empty()
+    }
+}
+
+/// Error returned when a lexicon document cannot be loaded.
+#[derive(Debug)]
+pub struct Error {
+    path: Option<PathBuf>,
+    kind: ErrorKind,
+}
+
+#[derive(Debug)]
+enum ErrorKind {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Schema(String),
+}
+
+impl Error {
+    fn io(path: &Path, err: io::Error) -> Self {
+        Error::from(ErrorKind::Io(err)).with_path(path)
+    }
+
+    fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_owned());
+        self
+    }
+
+    /// Returns the path of the offending file or directory, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error { path: None, kind }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        match &self.kind {
+            ErrorKind::Io(err) => err.fmt(f),
+            ErrorKind::Json(err) => write!(f, "invalid JSON: {}", err),
+            ErrorKind::Schema(msg) => write!(f, "invalid lexicon: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(err) => Some(err),
+            ErrorKind::Json(err) => Some(err),
+            ErrorKind::Schema(_) => None,
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! The parts of lexicon documents describing XRPC method parameters.
+
+use crate::formats::{self, Nsid};
+
+use serde_json::{Map, Value};
+use std::convert::TryFrom;
+
+/// The kind of an XRPC method.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MethodKind {
+    /// A `query`, called with HTTP GET.
+    Query,
+    /// A `procedure`, called with HTTP POST.
+    Procedure,
+    /// A `subscription`, called over a WebSocket.
+    Subscription,
+}
+
+/// The main definition of a lexicon document, if it is an XRPC method.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Method {
+    /// The NSID of the method, e.g. `app.bsky.feed.getAuthorFeed`.
+    pub id: String,
+    /// The kind of the method.
+    pub kind: MethodKind,
+    /// The description of the method, if any.
+    pub description: Option<String>,
+    /// The query parameters of the method.
+    pub parameters: Parameters,
+}
+
+/// The `parameters` of a method.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct Parameters {
+    /// The names of the required parameters.
+    pub required: Vec<String>,
+    /// Each parameter, sorted by name.
+    pub properties: Vec<(String, Property)>,
+}
+
+/// A single parameter.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Property {
+    /// The description of the parameter, if any.
+    pub description: Option<String>,
+    /// The type of the parameter and its constraints.
+    pub ty: PropertyType,
+}
+
+/// The type of a parameter, or of the items of an array parameter.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum PropertyType {
+    /// A `boolean`.
+    #[non_exhaustive]
+    Boolean {
+        /// The value used when the parameter is absent.
+        default: Option<bool>,
+    },
+    /// An `integer`.
+    #[non_exhaustive]
+    Integer {
+        /// The smallest accepted value.
+        minimum: Option<i64>,
+        /// The largest accepted value.
+        maximum: Option<i64>,
+        /// The only accepted values, if restricted.
+        enum_values: Option<Vec<i64>>,
+        /// The value used when the parameter is absent.
+        default: Option<i64>,
+    },
+    /// A `string`.
+    #[non_exhaustive]
+    String {
+        /// The string format, e.g. `did` or `at-uri`.
+        format: Option<String>,
+        /// The smallest accepted length, in UTF-8 bytes.
+        min_length: Option<usize>,
+        /// The largest accepted length, in UTF-8 bytes.
+        max_length: Option<usize>,
+        /// The values the lexicon suggests, which do not restrict the
+        /// accepted values.
+        known_values: Vec<String>,
+        /// The only accepted values, if restricted.
+        enum_values: Option<Vec<String>>,
+        /// The value used when the parameter is absent.
+        default: Option<String>,
+    },
+    /// An `array`, given as a repeated key.
+    #[non_exhaustive]
+    Array {
+        /// The type of the items, which is never an array.
+        items: Box<PropertyType>,
+        /// The smallest accepted number of items.
+        min_length: Option<usize>,
+        /// The largest accepted number of items.
+        max_length: Option<usize>,
+    },
+    /// An `unknown` value, which is accepted as any string.
+    Unknown,
+}
+
+impl Method {
+    /// Parses a lexicon document, returning `None` if its main definition is
+    /// not an XRPC method.
+    pub(super) fn parse(doc: &Value) -> Result<Option<Self>, String> {
+        let id = doc
+            .get("id")
+            .and_then(Value::as_str)
+            .ok_or("missing string `id`")?;
+        if let Some(Err(err)) = formats::validate(Nsid::FORMAT, id) {
+            return Err(format!("`id`: {}", err));
+        }
+        let main = match doc.get("defs").and_then(|defs| defs.get("main")) {
+            Some(main) => main,
+            None => return Ok(None),
+        };
+        let kind = match main.get("type").and_then(Value::as_str) {
+            Some("query") => MethodKind::Query,
+            Some("procedure") => MethodKind::Procedure,
+            Some("subscription") => MethodKind::Subscription,
+            _ => return Ok(None),
+        };
+        let parameters = match main.get("parameters") {
+            Some(parameters) => Parameters::parse(parameters)
+                .map_err(|err| format!("{}: {}", id, err))?,
+            None => Parameters::default(),
+        };
+        Ok(Some(Method {
+            id: id.to_owned(),
+            kind,
+            description: string(main, "description"),
+            parameters,
+        }))
+    }
+}
+
+impl Parameters {
+    fn parse(value: &Value) -> Result<Self, String> {
+        let required = match value.get("required") {
+            Some(required) => strings(required)
+                .ok_or("`required` is not an array of strings")?,
+            None => vec![],
+        };
+        let mut properties: Vec<_> = match value.get("properties") {
+            Some(Value::Object(properties)) => properties
+                .iter()
+                .map(|(name, property)| {
+                    if !is_param_name(name) {
+                        return Err(format!(
+                            "invalid parameter name `{}`",
+                            name
+                        ));
+                    }
+                    Property::parse(property)
+                        .map(|property| (name.clone(), property))
+                        .map_err(|err| format!("parameter `{}`: {}", name, err))
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err("`properties` is not an object".to_owned()),
+            None => vec![],
+        };
+        // Objects keep the document order with serde_json's
+        // `preserve_order` feature.
+        properties.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(Parameters {
+            required,
+            properties,
+        })
+    }
+
+    /// Returns the parameter named `name`, if any.
+    pub fn get(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|(property, _)| property == name)
+            .map(|(_, property)| property)
+    }
+
+    /// Returns whether the parameter named `name` is required.
+    pub fn is_required(&self, name: &str) -> bool {
+        self.required.iter().any(|required| required == name)
+    }
+}
+
+/// Returns whether `name` is an ASCII letter followed by ASCII letters,
+/// digits, `_` or `-`, which codegen can turn into a field name.
+fn is_param_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+impl Property {
+    fn parse(value: &Value) -> Result<Self, String> {
+        Ok(Property {
+            description: string(value, "description"),
+            ty: PropertyType::parse(value, true)?,
+        })
+    }
+}
+
+impl PropertyType {
+    fn parse(value: &Value, allow_array: bool) -> Result<Self, String> {
+        let value = value.as_object().ok_or("not an object")?;
+        let ty = value
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or("missing string `type`")?;
+        Ok(match ty {
+            "boolean" => PropertyType::Boolean {
+                default: value.get("default").and_then(Value::as_bool),
+            },
+            "integer" => PropertyType::Integer {
+                minimum: int(value, "minimum"),
+                maximum: int(value, "maximum"),
+                enum_values: value.get("enum").and_then(|values| {
+                    values.as_array()?.iter().map(Value::as_i64).collect()
+                }),
+                default: int(value, "default"),
+            },
+            "string" => PropertyType::String {
+                format: object_string(value, "format"),
+                min_length: length(value, "minLength"),
+                max_length: length(value, "maxLength"),
+                known_values: value
+                    .get("knownValues")
+                    .and_then(strings)
+                    .unwrap_or_default(),
+                enum_values: value.get("enum").and_then(strings),
+                default: object_string(value, "default"),
+            },
+            "array" if allow_array => PropertyType::Array {
+                items: Box::new(
+                    PropertyType::parse(
+                        value.get("items").ok_or("missing `items`")?,
+                        false,
+                    )
+                    .map_err(|err| format!("items: {}", err))?,
+                ),
+                min_length: length(value, "minLength"),
+                max_length: length(value, "maxLength"),
+            },
+            "unknown" => PropertyType::Unknown,
+            ty => return Err(format!("unsupported type `{}`", ty)),
+        })
+    }
+}
+
+fn string(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_owned)
+}
+
+fn object_string(value: &Map<String, Value>, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_owned)
+}
+
+fn int(value: &Map<String, Value>, key: &str) -> Option<i64> {
+    value.get(key).and_then(Value::as_i64)
+}
+
+fn length(value: &Map<String, Value>, key: &str) -> Option<usize> {
+    value
+        .get(key)
+        .and_then(Value::as_u64)
+        .map(|len| usize::try_from(len).unwrap_or(usize::MAX))
+}
+
+fn strings(value: &Value) -> Option<Vec<String>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|value| value.as_str().map(str::to_owned))
+        .collect()
+}
@@ -0,0 +1,347 @@
+//! Validation of query parameters against their lexicon.
+
+use crate::de::{self, is_canonical_integer};
+use crate::formats;
+use crate::lexicon::{Parameters, PropertyType};
+use crate::query_map::QueryMap;
+use crate::query_value::QueryValue;
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+
+/// A way in which a query does not conform to its lexicon.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    param: String,
+    index: Option<usize>,
+    kind: ViolationKind,
+    msg: String,
+}
+
+/// The category of a [`Violation`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ViolationKind {
+    /// A required parameter is absent.
+    Missing,
+    /// A parameter that is not an array is given more than once.
+    Repeated,
+    /// A value is not a valid boolean or integer.
+    InvalidType,
+    /// An integer is out of the `minimum` and `maximum` range.
+    OutOfRange,
+    /// A string is out of the `minLength` and `maxLength` range.
+    Length,
+    /// An array has fewer items than `minLength` or more than `maxLength`.
+    ItemCount,
+    /// A value is not in `enum`.
+    UnknownValue,
+    /// A string is not valid for its `format`.
+    InvalidFormat,
+    /// The query cannot be decoded with the given `de::Options`, e.g. it
+    /// exceeds a limit. No other violation is reported then.
+    Malformed,
+}
+
+impl Violation {
+    fn new(
+        param: &str,
+        index: Option<usize>,
+        kind: ViolationKind,
+        msg: impl fmt::Display,
+    ) -> Self {
+        Violation {
+            param: param.to_owned(),
+            index,
+            kind,
+            msg: msg.to_string(),
+        }
+    }
+
+    /// Returns the name of the offending parameter, which is empty if a
+    /// malformed query has none.
+    pub fn param(&self) -> &str {
+        &self.param
+    }
+
+    /// Returns the index of the offending item of an array parameter, if
+    /// any.
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// Returns the category of this violation.
+    pub fn kind(&self) -> ViolationKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.index {
+            // The message of a `de::Error` already names its key.
+            _ if self.kind == ViolationKind::Malformed => {
+                write!(f, "malformed query: {}", self.msg)
+            }
+            Some(index) => {
+                write!(f, "parameter `{}`[{}]: {}", self.param, index, self.msg)
+            }
+            None => write!(f, "parameter `{}`: {}", self.param, self.msg),
+        }
+    }
+}
+
+impl error::Error for Violation {}
+
+impl Parameters {
+    /// Validates a raw query string, e.g. `actor=alice.test&limit=10`,
+    /// decoded with the default `de::Options`.
+    ///
+    /// See [`Parameters::validate_map`].
+    pub fn validate(
+        &self,
+        query: &str,
+    ) -> Result<BTreeMap<String, QueryValue>, Vec<Violation>> {
+        self.validate_with(query, &de::Options::new())
+    }
+
+    /// Validates a raw query string decoded with `options`, so that their
+    /// limits and strict decoding apply.
+    ///
+    /// A query that cannot be decoded is a single
+    /// [`ViolationKind::Malformed`] violation, for the offending key if
+    /// known. Otherwise, see [`Parameters::validate_map`].
+    pub fn validate_with(
+        &self,
+        query: &str,
+        options: &de::Options,
+    ) -> Result<BTreeMap<String, QueryValue>, Vec<Violation>> {
+        let map: QueryMap = options.from_str(query).map_err(|err| {
+            let param = err.key().unwrap_or_default();
+            vec![Violation::new(param, None, ViolationKind::Malformed, &err)]
+        })?;
+        self.validate_map(&map)
+    }
+
+    /// Validates the parameters of `query`, returning every violation.
+    ///
+    /// Booleans must be `true` or `false`, and integers must be written as
+    /// in the atproto data model. Values must be in `enum` if given, while
+    /// `knownValues` only suggest strings and accept any other one, as in
+    /// atproto. Array parameters are given as repeated keys.
+    ///
+    /// Valid parameters are returned by name as typed values: booleans and
+    /// integers as such, arrays as lists even of a single item, and absent
+    /// parameters with a `default` as that default. Parameters missing from
+    /// the lexicon are kept unchecked, as strings or, if repeated, lists of
+    /// strings.
+    pub fn validate_map(
+        &self,
+        query: &QueryMap,
+    ) -> Result<BTreeMap<String, QueryValue>, Vec<Violation>> {
+        let mut violations = vec![];
+        for name in &self.required {
+            if !query.contains_key(name) {
+                violations.push(Violation::new(
+                    name,
+                    None,
+                    ViolationKind::Missing,
+                    "is required",
+                ));
+            }
+        }
+
+        let mut params = BTreeMap::new();
+        for (name, value) in query.iter() {
+            if self.get(name).is_some() {
+                continue;
+            }
+            let value = match params.remove(name) {
+                None => QueryValue::from(value),
+                Some(QueryValue::List(mut list)) => {
+                    list.push(value.into());
+                    QueryValue::List(list)
+                }
+                Some(first) => QueryValue::List(vec![first, value.into()]),
+            };
+            params.insert(name.to_owned(), value);
+        }
+
+        for (name, property) in &self.properties {
+            let values: Vec<&str> = query.get_all(name).collect();
+            if values.is_empty() {
+                if let Some(default) = default(&property.ty) {
+                    params.insert(name.clone(), default);
+                }
+                continue;
+            }
+            match &property.ty {
+                PropertyType::Array {
+                    items,
+                    min_length,
+                    max_length,
+                } => {
+                    let len = values.len();
+                    if let Some(min) = min_length.filter(|&min| len < min) {
+                        violations.push(Violation::new(
+                            name,
+                            None,
+                            ViolationKind::ItemCount,
+                            format_args!("has fewer than {} items", min),
+                        ));
+                    }
+                    if let Some(max) = max_length.filter(|&max| len > max) {
+                        violations.push(Violation::new(
+                            name,
+                            None,
+                            ViolationKind::ItemCount,
+                            format_args!("has more than {} items", max),
+                        ));
+                    }
+                    let mut list = Vec::with_capacity(len);
+                    for (index, value) in values.into_iter().enumerate() {
+                        match check_value(items, value) {
+                            Ok(value) => list.push(value),
+                            Err((kind, msg)) => violations.push(
+                                Violation::new(name, Some(index), kind, msg),
+                            ),
+                        }
+                    }
+                    params.insert(name.clone(), QueryValue::List(list));
+                }
+                ty => {
+                    if values.len() > 1 {
+                        violations.push(Violation::new(
+                            name,
+                            None,
+                            ViolationKind::Repeated,
+                            "is given more than once",
+                        ));
+                    }
+                    match check_value(ty, values[0]) {
+                        Ok(value) => {
+                            params.insert(name.clone(), value);
+                        }
+                        Err((kind, msg)) => violations
+                            .push(Violation::new(name, None, kind, msg)),
+                    }
+                }
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(params)
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+/// Checks a single value against a type other than an array, and returns it
+/// typed.
+fn check_value(
+    ty: &PropertyType,
+    value: &str,
+) -> Result<QueryValue, (ViolationKind, String)> {
+    match ty {
+        PropertyType::Boolean { .. } => match value {
+            "true" => Ok(QueryValue::Bool(true)),
+            "false" => Ok(QueryValue::Bool(false)),
+            _ => Err((
+                ViolationKind::InvalidType,
+                format!("expected `true` or `false`, found `{}`", value),
+            )),
+        },
+        PropertyType::Integer {
+            minimum,
+            maximum,
+            enum_values,
+            ..
+        } => {
+            let int = match value.parse::<i64>() {
+                Ok(int) if is_canonical_integer(value) => int,
+                _ => {
+                    return Err((
+                        ViolationKind::InvalidType,
+                        format!("expected an integer, found `{}`", value),
+                    ))
+                }
+            };
+            if let Some(min) = minimum.filter(|&min| int < min) {
+                return Err((
+                    ViolationKind::OutOfRange,
+                    format!("{} is less than the minimum {}", int, min),
+                ));
+            }
+            if let Some(max) = maximum.filter(|&max| int > max) {
+                return Err((
+                    ViolationKind::OutOfRange,
+                    format!("{} is greater than the maximum {}", int, max),
+                ));
+            }
+            match enum_values {
+                Some(values) if !values.contains(&int) => Err((
+                    ViolationKind::UnknownValue,
+                    format!("{} is not an accepted value", int),
+                )),
+                _ => Ok(QueryValue::Integer(int)),
+            }
+        }
+        PropertyType::String {
+            format,
+            min_length,
+            max_length,
+            enum_values,
+            ..
+        } => {
+            let len = value.len();
+            if let Some(min) = min_length.filter(|&min| len < min) {
+                return Err((
+                    ViolationKind::Length,
+                    format!("is shorter than {} bytes", min),
+                ));
+            }
+            if let Some(max) = max_length.filter(|&max| len > max) {
+                return Err((
+                    ViolationKind::Length,
+                    format!("is longer than {} bytes", max),
+                ));
+            }
+            if let Some(values) = enum_values {
+                if !values.iter().any(|known| known == value) {
+                    return Err((
+                        ViolationKind::UnknownValue,
+                        format!("`{}` is not an accepted value", value),
+                    ));
+                }
+            }
+            match format
+                .as_deref()
+                .and_then(|format| formats::validate(format, value))
+            {
+                Some(Err(err)) => {
+                    Err((ViolationKind::InvalidFormat, err.to_string()))
+                }
+                _ => Ok(QueryValue::from(value)),
+            }
+        }
+        PropertyType::Array { .. } | PropertyType::Unknown => {
+            Ok(QueryValue::from(value))
+        }
+    }
+}
+
+/// Returns the `default` of a type, if any.
+fn default(ty: &PropertyType) -> Option<QueryValue> {
+    match ty {
+        PropertyType::Boolean { default } => default.map(QueryValue::Bool),
+        PropertyType::Integer { default, .. } => {
+            default.map(QueryValue::Integer)
+        }
+        PropertyType::String { default, .. } => {
+            default.clone().map(QueryValue::String)
+        }
+        PropertyType::Array { .. } | PropertyType::Unknown => None,
+    }
+}
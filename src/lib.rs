@@ -3,8 +3,20 @@
 #![warn(unused_extern_crates)]
 #![forbid(unsafe_code)]
 
+pub mod bounded;
 pub mod de;
+pub mod formats;
+#[cfg(feature = "json")]
+pub mod json;
+#[cfg(feature = "lexicon")]
+pub mod lexicon;
+mod nesting;
+pub mod open_enum;
+pub mod query_map;
+pub mod query_value;
+pub mod seq_style;
 pub mod ser;
+pub mod with;
 
 #[doc(inline)]
 pub use crate::de::{from_bytes, from_reader, from_str, Deserializer};
@@ -0,0 +1,55 @@
+/// How the keys of nested structs, maps and sequences of structs are written.
+///
+/// Used by `ser::Options::nesting` and `de::Options::nesting`, which are
+/// disabled by default. Sequences of scalars are unaffected and still use
+/// repeated keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Nesting {
+    /// `filter.author=x`, and `items.0.name=x` for sequences of structs.
+    ///
+    /// With `SeqStyle::Bracketed` or `SeqStyle::Indexed`, sequences are
+    /// written with brackets instead, e.g. `items[0].name=x`, which is read
+    /// back as well.
+    Dot,
+    /// `filter[author]=x`, and `items[][name]=x` for sequences of structs.
+    ///
+    /// When deserializing, a new element starts whenever a field of the
+    /// current one is repeated, so elements whose first field is `None` are
+    /// merged with the previous one. Use `IndexedBracket` if that matters.
+    Bracket,
+    /// `filter[author]=x`, and `items[0][name]=x` for sequences of structs.
+    IndexedBracket,
+}
+
+impl Nesting {
+    /// Appends the key of a field or map entry to the key of its parent.
+    pub(crate) fn push_field(self, key: &mut String, field: &str) {
+        match self {
+            Nesting::Dot => {
+                key.push('.');
+                key.push_str(field);
+            }
+            Nesting::Bracket | Nesting::IndexedBracket => {
+                key.push('[');
+                key.push_str(field);
+                key.push(']');
+            }
+        }
+    }
+
+    /// Appends the key of a sequence element to the key of the sequence.
+    pub(crate) fn push_index(self, key: &mut String, index: usize) {
+        match self {
+            Nesting::Dot => {
+                key.push('.');
+                key.push_str(itoa::Buffer::new().format(index));
+            }
+            Nesting::Bracket => key.push_str("[]"),
+            Nesting::IndexedBracket => {
+                key.push('[');
+                key.push_str(itoa::Buffer::new().format(index));
+                key.push(']');
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! String enums that accept values beyond their known variants.
+
+use serde::de::value::{Error as ValueError, StrDeserializer};
+use serde::de::{self, Deserialize, Deserializer, IntoDeserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A string with lexicon `knownValues`: either one of the unit variants of
+/// `E`, or any other string.
+///
+/// Values are matched against `E` with its own `Deserialize` impl, so
+/// `#[serde(rename)]` and `#[serde(rename_all)]` apply. Unmatched strings are
+/// kept as `Other` and serialized back out unchanged.
+///
+/// ```
+/// use serde_derive::{Deserialize, Serialize};
+/// use serde_urlencoded_xrpc::open_enum::OpenEnum;
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// #[serde(rename_all = "snake_case")]
+/// enum Filter {
+///     PostsWithReplies,
+///     PostsNoReplies,
+/// }
+///
+/// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+/// struct Params {
+///     filter: OpenEnum<Filter>,
+/// }
+///
+/// let params: Params =
+///     serde_urlencoded_xrpc::from_str("filter=posts_with_replies").unwrap();
+/// assert_eq!(params.filter, OpenEnum::Known(Filter::PostsWithReplies));
+///
+/// let params: Params =
+///     serde_urlencoded_xrpc::from_str("filter=posts_with_video").unwrap();
+/// assert_eq!(params.filter, OpenEnum::Other("posts_with_video".to_owned()));
+/// assert_eq!(
+///     serde_urlencoded_xrpc::to_string(&params),
+///     Ok("filter=posts_with_video".to_owned()),
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OpenEnum<E> {
+    /// One of the known values.
+    Known(E),
+    /// Any other value.
+    Other(String),
+}
+
+impl<E> OpenEnum<E> {
+    /// Returns the known value, if any.
+    pub fn known(&self) -> Option<&E> {
+        match self {
+            OpenEnum::Known(known) => Some(known),
+            OpenEnum::Other(_) => None,
+        }
+    }
+
+    /// Returns whether the value is one of the known values.
+    pub fn is_known(&self) -> bool {
+        self.known().is_some()
+    }
+}
+
+impl<E> From<E> for OpenEnum<E> {
+    fn from(known: E) -> Self {
+        OpenEnum::Known(known)
+    }
+}
+
+impl<E: Serialize> Serialize for OpenEnum<E> {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match self {
+            OpenEnum::Known(known) => known.serialize(serializer),
+            OpenEnum::Other(other) => serializer.serialize_str(other),
+        }
+    }
+}
+
+impl<'de, E: Deserialize<'de>> Deserialize<'de> for OpenEnum<E> {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(OpenEnumVisitor(PhantomData))
+    }
+}
+
+struct OpenEnumVisitor<E>(PhantomData<E>);
+
+impl<'de, E: Deserialize<'de>> Visitor<'de> for OpenEnumVisitor<E> {
+    type Value = OpenEnum<E>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<Err: de::Error>(self, v: &str) -> Result<Self::Value, Err> {
+        let deserializer: StrDeserializer<'_, ValueError> =
+            v.into_deserializer();
+        Ok(match E::deserialize(deserializer) {
+            Ok(known) => OpenEnum::Known(known),
+            Err(_) => OpenEnum::Other(v.to_owned()),
+        })
+    }
+}
@@ -0,0 +1,287 @@
+//! Queries held as ordered key-value pairs, without a concrete struct.
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+use std::iter::FromIterator;
+use std::vec;
+
+/// The name `QueryMap` deserializes as a newtype struct with, which lets
+/// `de::Deserializer` provide its pairs in input order.
+pub(crate) const NAME: &str = "$serde_urlencoded_xrpc::QueryMap";
+
+/// An ordered multimap of query parameters.
+///
+/// Pairs are kept in insertion order, including repeated keys, so that a
+/// query can be forwarded or logged unchanged. It serializes as a map with
+/// an entry per pair, and `de::Deserializer` provides it every pair in input
+/// order.
+///
+/// ```
+/// use serde_urlencoded_xrpc::query_map::QueryMap;
+///
+/// let mut query: QueryMap =
+///     serde_urlencoded_xrpc::from_str("tag=a&limit=5&tag=b").unwrap();
+/// assert_eq!(query.get("tag"), Some("a"));
+/// assert_eq!(query.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+///
+/// query.insert("limit", "10");
+/// query.append("tag", "c");
+/// assert_eq!(
+///     serde_urlencoded_xrpc::to_string(&query),
+///     Ok("tag=a&limit=10&tag=b&tag=c".to_owned()),
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct QueryMap {
+    pairs: Vec<(String, String)>,
+}
+
+impl QueryMap {
+    /// Returns an empty map.
+    pub fn new() -> Self {
+        QueryMap::default()
+    }
+
+    /// Returns the number of pairs, counting each value of a repeated key.
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns whether the map has no pairs.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Returns whether the map has a value for `key`.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the first value for `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.iter().find(|&(k, _)| k == key).map(|(_, value)| value)
+    }
+
+    /// Returns every value for `key`, in order.
+    pub fn get_all<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        self.iter()
+            .filter(move |&(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Sets the value for `key`, replacing any previous values.
+    ///
+    /// The value takes the place of the first previous value, or is added
+    /// at the end. Returns the first previous value, if any.
+    pub fn insert(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Option<String> {
+        let key = key.into();
+        let mut value = Some(value.into());
+        let mut previous = None;
+        self.pairs.retain_mut(|(k, v)| {
+            if *k != key {
+                return true;
+            }
+            match value.take() {
+                Some(value) => {
+                    previous = Some(std::mem::replace(v, value));
+                    true
+                }
+                None => false,
+            }
+        });
+        if let Some(value) = value {
+            self.pairs.push((key, value));
+        }
+        previous
+    }
+
+    /// Adds a value for `key` at the end, keeping any previous values.
+    pub fn append(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.pairs.push((key.into(), value.into()));
+    }
+
+    /// Removes every value for `key`, and returns them in order.
+    pub fn remove(&mut self, key: &str) -> Vec<String> {
+        let mut removed = vec![];
+        let pairs = std::mem::take(&mut self.pairs);
+        for (k, v) in pairs {
+            if k == key {
+                removed.push(v);
+            } else {
+                self.pairs.push((k, v));
+            }
+        }
+        removed
+    }
+
+    /// Returns an iterator over the pairs, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl From<Vec<(String, String)>> for QueryMap {
+    fn from(pairs: Vec<(String, String)>) -> Self {
+        QueryMap { pairs }
+    }
+}
+
+impl From<QueryMap> for Vec<(String, String)> {
+    fn from(map: QueryMap) -> Self {
+        map.pairs
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for QueryMap {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = QueryMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> Extend<(K, V)> for QueryMap {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.append(key, value);
+        }
+    }
+}
+
+impl IntoIterator for QueryMap {
+    type Item = (String, String);
+    type IntoIter = vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.pairs.into_iter()
+    }
+}
+
+impl Serialize for QueryMap {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for QueryMap {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(NAME, QueryMapVisitor)
+    }
+}
+
+struct QueryMapVisitor;
+
+impl<'de> de::Visitor<'de> for QueryMapVisitor {
+    type Value = QueryMap;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a map of strings")
+    }
+
+    /// Formats other than this crate's deserialize the map as usual.
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<QueryMap, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+
+    /// Receives the pairs of `de::Deserializer` in input order.
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<QueryMap, A::Error> {
+        let mut map = QueryMap::new();
+        while let Some(pair) = seq.next_element::<(String, String)>()? {
+            map.pairs.push(pair);
+        }
+        Ok(map)
+    }
+
+    /// Receives the entries of a map, whose values may be lists when keys
+    /// are grouped, e.g. for a `#[serde(flatten)]` field.
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut access: A,
+    ) -> Result<QueryMap, A::Error> {
+        let mut map = QueryMap::new();
+        while let Some((key, Values(values))) =
+            access.next_entry::<String, Values>()?
+        {
+            for value in values {
+                map.append(key.clone(), value);
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// A single value or a list of values, accepting booleans and integers for
+/// the sake of `de::Options::infer_types`.
+struct Values(Vec<String>);
+
+impl<'de> Deserialize<'de> for Values {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValuesVisitor)
+    }
+}
+
+struct ValuesVisitor;
+
+impl<'de> de::Visitor<'de> for ValuesVisitor {
+    type Value = Values;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string or a list of strings")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Values, E> {
+        Ok(Values(vec![v.to_owned()]))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Values, E> {
+        Ok(Values(vec![v]))
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Values, E> {
+        Ok(Values(vec![v.to_string()]))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Values, E> {
+        Ok(Values(vec![v.to_string()]))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Values, E> {
+        Ok(Values(vec![v.to_string()]))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<Values, A::Error> {
+        let mut values = vec![];
+        while let Some(Values(value)) = seq.next_element()? {
+            values.extend(value);
+        }
+        Ok(Values(values))
+    }
+}
@@ -0,0 +1,248 @@
+//! Query parameter values without a concrete type.
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The value of a query parameter, for queries without a Rust type.
+///
+/// Deserializing a map of `QueryValue`s gives a `List` for every repeated
+/// key. Single values are `String`s, unless [`de::Options::infer_types`] is
+/// set, in which case `true`, `false` and integers in the `i64` range are
+/// `Bool`s and `Integer`s.
+///
+/// [`de::Options::infer_types`]: crate::de::Options::infer_types
+///
+/// ```
+/// use serde_urlencoded_xrpc::de::Options;
+/// use serde_urlencoded_xrpc::query_value::QueryValue;
+/// use std::collections::BTreeMap;
+///
+/// let mut params: BTreeMap<String, QueryValue> = Options::new()
+///     .infer_types(true)
+///     .from_str("actor=alice&limit=500&tag=a&tag=b")
+///     .unwrap();
+///
+/// if let Some(QueryValue::Integer(limit)) = params.get_mut("limit") {
+///     *limit = (*limit).min(100);
+/// }
+/// assert_eq!(
+///     serde_urlencoded_xrpc::to_string(&params),
+///     Ok("actor=alice&limit=100&tag=a&tag=b".to_owned()),
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum QueryValue {
+    /// A string, or any value not inferred as something else.
+    String(String),
+    /// An integer.
+    Integer(i64),
+    /// A boolean.
+    Bool(bool),
+    /// The values of a repeated key.
+    List(Vec<QueryValue>),
+}
+
+impl QueryValue {
+    /// Returns the string, if this is one.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            QueryValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer, if this is one.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            QueryValue::Integer(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, if this is one.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            QueryValue::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the values, if this is a list.
+    pub fn as_list(&self) -> Option<&[QueryValue]> {
+        match self {
+            QueryValue::List(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for QueryValue {
+    fn from(value: String) -> Self {
+        QueryValue::String(value)
+    }
+}
+
+impl From<&str> for QueryValue {
+    fn from(value: &str) -> Self {
+        QueryValue::String(value.to_owned())
+    }
+}
+
+impl From<i64> for QueryValue {
+    fn from(value: i64) -> Self {
+        QueryValue::Integer(value)
+    }
+}
+
+impl From<bool> for QueryValue {
+    fn from(value: bool) -> Self {
+        QueryValue::Bool(value)
+    }
+}
+
+impl<T: Into<QueryValue>> From<Vec<T>> for QueryValue {
+    fn from(values: Vec<T>) -> Self {
+        QueryValue::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl Serialize for QueryValue {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match self {
+            QueryValue::String(value) => serializer.serialize_str(value),
+            QueryValue::Integer(value) => serializer.serialize_i64(*value),
+            QueryValue::Bool(value) => serializer.serialize_bool(*value),
+            QueryValue::List(values) => serializer.collect_seq(values),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for QueryValue {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(QueryValueVisitor)
+    }
+}
+
+struct QueryValueVisitor;
+
+impl<'de> Visitor<'de> for QueryValueVisitor {
+    type Value = QueryValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string, integer, boolean or list")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<QueryValue, E> {
+        Ok(QueryValue::String(v.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<QueryValue, E> {
+        Ok(QueryValue::String(v))
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<QueryValue, E> {
+        Ok(QueryValue::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<QueryValue, E> {
+        Ok(QueryValue::Integer(v))
+    }
+
+    /// Integers beyond the `i64` range are kept as strings.
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<QueryValue, E> {
+        Ok(match i64::try_from(v) {
+            Ok(v) => QueryValue::Integer(v),
+            Err(_) => QueryValue::String(v.to_string()),
+        })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<QueryValue, A::Error> {
+        let mut values = vec![];
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(QueryValue::List(values))
+    }
+}
+
+#[cfg(feature = "json")]
+mod json {
+    use super::QueryValue;
+
+    use serde_json::Value;
+    use std::convert::TryFrom;
+    use std::error;
+    use std::fmt;
+
+    impl From<QueryValue> for Value {
+        fn from(value: QueryValue) -> Self {
+            match value {
+                QueryValue::String(value) => Value::String(value),
+                QueryValue::Integer(value) => Value::from(value),
+                QueryValue::Bool(value) => Value::Bool(value),
+                QueryValue::List(values) => {
+                    values.into_iter().map(Value::from).collect()
+                }
+            }
+        }
+    }
+
+    impl TryFrom<Value> for QueryValue {
+        type Error = FromJsonError;
+
+        /// Converts strings, booleans, integers in the `i64` range and
+        /// arrays of those. Nested arrays are accepted, although they cannot
+        /// be serialized as a query.
+        fn try_from(value: Value) -> Result<Self, FromJsonError> {
+            match value {
+                Value::String(value) => Ok(QueryValue::String(value)),
+                Value::Bool(value) => Ok(QueryValue::Bool(value)),
+                Value::Number(number) => match number.as_i64() {
+                    Some(value) => Ok(QueryValue::Integer(value)),
+                    None => Err(FromJsonError { kind: "number" }),
+                },
+                Value::Array(values) => values
+                    .into_iter()
+                    .map(QueryValue::try_from)
+                    .collect::<Result<_, _>>()
+                    .map(QueryValue::List),
+                Value::Null => Err(FromJsonError { kind: "null" }),
+                Value::Object(_) => Err(FromJsonError { kind: "object" }),
+            }
+        }
+    }
+
+    /// Error returned when a JSON value has no `QueryValue` equivalent.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct FromJsonError {
+        kind: &'static str,
+    }
+
+    impl fmt::Display for FromJsonError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.kind {
+                "number" => f.write_str(
+                    "JSON number is not an integer in the i64 range",
+                ),
+                kind => {
+                    write!(f, "JSON {} has no query value equivalent", kind)
+                }
+            }
+        }
+    }
+
+    impl error::Error for FromJsonError {}
+}
+
+#[cfg(feature = "json")]
+pub use self::json::FromJsonError;
@@ -0,0 +1,161 @@
+//! How sequences of scalars are written, and `with` modules to choose it
+//! per field.
+//!
+//! ```
+//! use serde_derive::{Deserialize, Serialize};
+//! use serde_urlencoded_xrpc::seq_style;
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Params {
+//!     #[serde(with = "seq_style::comma_separated")]
+//!     tags: Vec<String>,
+//!     #[serde(with = "seq_style::indexed")]
+//!     ids: Vec<u32>,
+//! }
+//!
+//! let params = Params {
+//!     tags: vec!["a".to_owned(), "b".to_owned()],
+//!     ids: vec![1, 2],
+//! };
+//! let query = "tags=a%2Cb&ids%5B0%5D=1&ids%5B1%5D=2";
+//! assert_eq!(serde_urlencoded_xrpc::to_string(&params), Ok(query.to_owned()));
+//! assert_eq!(serde_urlencoded_xrpc::from_str(query), Ok(params));
+//! ```
+
+/// How the elements of a sequence are written.
+///
+/// Used by `ser::Options::seq_style` and `de::Options::seq_style`, and
+/// overridden for a single field by the `with` modules of this module.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SeqStyle {
+    /// A pair per element with the same key: `tags=a&tags=b`.
+    #[default]
+    Repeated,
+    /// A single pair with the elements separated by commas: `tags=a,b`.
+    ///
+    /// Serializing an element that contains a comma is an error.
+    CommaSeparated,
+    /// A pair per element, with `[]` after the key: `tags[]=a&tags[]=b`.
+    Bracketed,
+    /// A pair per element, with its index after the key:
+    /// `tags[0]=a&tags[1]=b`.
+    Indexed,
+}
+
+impl SeqStyle {
+    /// Returns the style overriding the default for a field, given the name
+    /// of the newtype wrapping its value.
+    pub(crate) fn from_newtype_name(name: &str) -> Option<Self> {
+        match name {
+            repeated::NAME => Some(SeqStyle::Repeated),
+            comma_separated::NAME => Some(SeqStyle::CommaSeparated),
+            bracketed::NAME => Some(SeqStyle::Bracketed),
+            indexed::NAME => Some(SeqStyle::Indexed),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a key such as `tags[]` or `tags[0]` into `tags` and the index, if
+/// any.
+pub(crate) fn split_element_key(key: &str) -> Option<(&str, Option<usize>)> {
+    let base = key.strip_suffix(']')?;
+    let (base, index) = base.rsplit_once('[')?;
+    if base.is_empty() {
+        return None;
+    }
+    if index.is_empty() {
+        return Some((base, None));
+    }
+    if !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    index.parse().ok().map(|index| (base, Some(index)))
+}
+
+macro_rules! seq_style_module {
+    ($(#[$attr:meta])* $module:ident, $name:literal) => {
+        $(#[$attr])*
+        ///
+        /// Other formats serialize and deserialize the field as usual.
+        pub mod $module {
+            use serde::de::{Deserialize, Deserializer, Visitor};
+            use serde::ser::{Serialize, Serializer};
+            use std::fmt;
+            use std::marker::PhantomData;
+
+            pub(crate) const NAME: &str = $name;
+
+            /// Serializes `value` with this sequence style.
+            pub fn serialize<T, S>(
+                value: &T,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                T: ?Sized + Serialize,
+                S: Serializer,
+            {
+                serializer.serialize_newtype_struct(NAME, value)
+            }
+
+            /// Deserializes a value written with this sequence style.
+            pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+            where
+                T: Deserialize<'de>,
+                D: Deserializer<'de>,
+            {
+                struct StyleVisitor<T>(PhantomData<T>);
+
+                impl<'de, T: Deserialize<'de>> Visitor<'de> for StyleVisitor<T> {
+                    type Value = T;
+
+                    fn expecting(
+                        &self,
+                        f: &mut fmt::Formatter<'_>,
+                    ) -> fmt::Result {
+                        f.write_str("a sequence")
+                    }
+
+                    fn visit_newtype_struct<D: Deserializer<'de>>(
+                        self,
+                        deserializer: D,
+                    ) -> Result<T, D::Error> {
+                        T::deserialize(deserializer)
+                    }
+                }
+
+                deserializer
+                    .deserialize_newtype_struct(NAME, StyleVisitor(PhantomData))
+            }
+        }
+    };
+}
+
+seq_style_module! {
+    /// Writes a sequence field with [`SeqStyle::Repeated`]: `tags=a&tags=b`.
+    repeated, "$serde_urlencoded_xrpc::seq_style::Repeated"
+}
+
+seq_style_module! {
+    /// Writes a sequence field with [`SeqStyle::CommaSeparated`]:
+    /// `tags=a,b`.
+    ///
+    /// Elements go through their `Serialize` and `Deserialize` impls, and
+    /// other formats, e.g. JSON, see a regular sequence. To keep the field a
+    /// single `a,b` string in every format, with elements written through
+    /// `Display` and `FromStr`, use
+    /// [`with::comma_joined`](crate::with::comma_joined) instead.
+    comma_separated, "$serde_urlencoded_xrpc::seq_style::CommaSeparated"
+}
+
+seq_style_module! {
+    /// Writes a sequence field with [`SeqStyle::Bracketed`]:
+    /// `tags[]=a&tags[]=b`.
+    bracketed, "$serde_urlencoded_xrpc::seq_style::Bracketed"
+}
+
+seq_style_module! {
+    /// Writes a sequence field with [`SeqStyle::Indexed`]:
+    /// `tags[0]=a&tags[1]=b`.
+    indexed, "$serde_urlencoded_xrpc::seq_style::Indexed"
+}
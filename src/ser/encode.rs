@@ -0,0 +1,105 @@
+use crate::ser::Sink;
+
+use form_urlencoded::byte_serialize;
+
+/// How keys and values are percent-encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// The `application/x-www-form-urlencoded` encoding: spaces are written
+    /// as `+`, and everything but ASCII alphanumerics and `*-._` is escaped.
+    #[default]
+    Form,
+    /// Query component encoding per RFC 3986: spaces are written as `%20`,
+    /// and characters allowed in a query are left literal, except for `&`,
+    /// `=` and `+`, which are escaped.
+    ///
+    /// This keeps values such as `at://did:plc:xyz/app.bsky.feed.post/3k`
+    /// readable.
+    Rfc3986,
+    /// Escapes everything but the RFC 3986 unreserved characters, ASCII
+    /// alphanumerics and `-._~`. Spaces are written as `%20`.
+    Strict,
+}
+
+impl Encoding {
+    fn append_encoded(self, input: &str, output: &mut String) {
+        let is_literal = match self {
+            Encoding::Form => {
+                output.extend(byte_serialize(input.as_bytes()));
+                return;
+            }
+            Encoding::Rfc3986 => is_query_literal,
+            Encoding::Strict => is_unreserved,
+        };
+
+        for &byte in input.as_bytes() {
+            if is_literal(byte) {
+                output.push(char::from(byte));
+            } else {
+                output.push('%');
+                output.push(char::from(HEX_DIGITS[usize::from(byte >> 4)]));
+                output.push(char::from(HEX_DIGITS[usize::from(byte & 0xF)]));
+            }
+        }
+    }
+}
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+/// Whether `byte` may appear literally in a query parameter: RFC 3986 allows
+/// `pchar / "/" / "?"` in a query, minus the `&`, `=` and `+` delimiters.
+fn is_query_literal(byte: u8) -> bool {
+    is_unreserved(byte)
+        || matches!(
+            byte,
+            b':' | b'@'
+                | b'/'
+                | b'?'
+                | b'!'
+                | b'$'
+                | b'\''
+                | b'('
+                | b')'
+                | b'*'
+                | b','
+                | b';'
+        )
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// A `Sink` that percent-encodes pairs into a `String` using the given
+/// `Encoding`.
+#[derive(Clone, Debug, Default)]
+pub struct Encoder {
+    output: String,
+    encoding: Encoding,
+}
+
+impl Encoder {
+    /// Returns a new, empty `Encoder`.
+    pub fn new(encoding: Encoding) -> Self {
+        Encoder {
+            output: String::new(),
+            encoding,
+        }
+    }
+
+    /// Returns the encoded pairs.
+    pub fn finish(self) -> String {
+        self.output
+    }
+}
+
+impl Sink for Encoder {
+    fn append_pair(&mut self, key: &str, value: &str) {
+        if !self.output.is_empty() {
+            self.output.push('&');
+        }
+        self.encoding.append_encoded(key, &mut self.output);
+        self.output.push('=');
+        self.encoding.append_encoded(value, &mut self.output);
+    }
+}
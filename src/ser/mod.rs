@@ -1,7 +1,14 @@
 //! Serialization support for the `application/x-www-form-urlencoded` format.
 
+mod encode;
+mod options;
 mod value;
 
+pub use self::encode::{Encoder, Encoding};
+pub use self::options::Options;
+pub use crate::nesting::Nesting;
+pub use crate::seq_style::SeqStyle;
+
 use form_urlencoded::Serializer as UrlEncodedSerializer;
 use form_urlencoded::Target as UrlEncodedTarget;
 use serde::ser;
@@ -35,40 +42,81 @@ use std::str;
 ///     Ok("bread=baguette&cheese=comt%C3%A9&meat=ham&fat=butter".to_owned()));
 /// ```
 pub fn to_string<T: ser::Serialize>(input: T) -> Result<String, Error> {
-    let mut urlencoder = UrlEncodedSerializer::new("".to_owned());
-    input.serialize(Serializer::new(&mut urlencoder))?;
-    Ok(urlencoder.finish())
+    Options::default().to_string(input)
+}
+
+/// A destination for the key-value pairs written by a [`Serializer`].
+///
+/// Implemented by [`Encoder`], and by `form_urlencoded::Serializer` so that
+/// values can be serialized into e.g. `Url::query_pairs_mut`.
+pub trait Sink {
+    /// Appends a key-value pair, percent-encoding both as needed.
+    fn append_pair(&mut self, key: &str, value: &str);
+}
+
+impl<'input, Target: UrlEncodedTarget> Sink
+    for UrlEncodedSerializer<'input, Target>
+{
+    fn append_pair(&mut self, key: &str, value: &str) {
+        UrlEncodedSerializer::append_pair(self, key, value);
+    }
 }
 
 /// A serializer for the `application/x-www-form-urlencoded` format.
 ///
-/// * Supported top-level inputs are structs, maps and sequences of pairs,
-///   with or without a given length.
+/// * Supported top-level inputs are structs and maps, including structs with
+///   `#[serde(flatten)]` fields.
 ///
 /// * Supported keys and values are integers, bytes (if convertible to strings),
-///   unit structs and unit variants.
+///   unit structs and unit variants. Values may also be sequences of those,
+///   written as set by [`Options::seq_style`], and enum variants with data,
+///   which are written as the variant name
+///   followed by each field, separated by `:` (see
+///   [`Options::variant_separator`]).
+///
+/// * Values may be structs and maps if [`Options::nesting`] is set, and
+///   are then written as a key for each of their fields.
 ///
 /// * Newtype structs defer to their inner values.
-pub struct Serializer<'input, 'output, Target: UrlEncodedTarget> {
-    urlencoder: &'output mut UrlEncodedSerializer<'input, Target>,
+pub struct Serializer<'output, Target: Sink> {
+    urlencoder: &'output mut Target,
+    options: Options,
 }
 
-impl<'input, 'output, Target: 'output + UrlEncodedTarget>
-    Serializer<'input, 'output, Target>
-{
+impl<'output, Target: 'output + Sink> Serializer<'output, Target> {
     /// Returns a new `Serializer`.
-    pub fn new(
-        urlencoder: &'output mut UrlEncodedSerializer<'input, Target>,
+    pub fn new(urlencoder: &'output mut Target) -> Self {
+        Serializer::with_options(urlencoder, Options::default())
+    }
+
+    /// Returns a new `Serializer` using the given options.
+    ///
+    /// The encoding of the options is ignored, `urlencoder` does the
+    /// percent-encoding.
+    pub fn with_options(
+        urlencoder: &'output mut Target,
+        options: Options,
     ) -> Self {
-        Serializer { urlencoder }
+        Serializer {
+            urlencoder,
+            options,
+        }
     }
 }
 
 /// Errors returned during serializing to `application/x-www-form-urlencoded`.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum Error {
     Custom(Cow<'static, str>),
     Utf8(str::Utf8Error),
+    /// An error that occurred while serializing the value of a field, or the
+    /// element at `index` if the field is a sequence.
+    Field {
+        key: String,
+        index: Option<usize>,
+        error: Box<Error>,
+    },
 }
 
 impl fmt::Display for Error {
@@ -76,16 +124,28 @@ impl fmt::Display for Error {
         match *self {
             Error::Custom(ref msg) => msg.fmt(f),
             Error::Utf8(ref err) => write!(f, "invalid UTF-8: {}", err),
+            Error::Field {
+                ref key,
+                index: Some(index),
+                ref error,
+            } => write!(f, "field `{}`[{}]: {}", key, index, error),
+            Error::Field {
+                ref key,
+                index: None,
+                ref error,
+            } => write!(f, "field `{}`: {}", key, error),
         }
     }
 }
 
 impl error::Error for Error {
-    /// The lower-level source of this error, in the case of a `Utf8` error.
+    /// The lower-level source of this error, in the case of a `Utf8` or
+    /// `Field` error.
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             Error::Custom(_) => None,
             Error::Utf8(ref err) => Some(err),
+            Error::Field { ref error, .. } => Some(&**error),
         }
     }
 }
@@ -97,23 +157,23 @@ impl ser::Error for Error {
 }
 
 /// Struct serializer.
-pub struct StructSerializer<'input, 'output, Target: UrlEncodedTarget> {
-    urlencoder: &'output mut UrlEncodedSerializer<'input, Target>,
+pub struct StructSerializer<'output, Target: Sink> {
+    urlencoder: &'output mut Target,
+    options: Options,
 }
 
-impl<'input, 'output, Target> ser::Serializer
-    for Serializer<'input, 'output, Target>
+impl<'output, Target> ser::Serializer for Serializer<'output, Target>
 where
-    Target: 'output + UrlEncodedTarget,
+    Target: 'output + Sink,
 {
-    type Ok = &'output mut UrlEncodedSerializer<'input, Target>;
+    type Ok = &'output mut Target;
     type Error = Error;
     type SerializeSeq = ser::Impossible<Self::Ok, Self::Error>;
     type SerializeTuple = ser::Impossible<Self::Ok, Self::Error>;
     type SerializeTupleStruct = ser::Impossible<Self::Ok, Self::Error>;
     type SerializeTupleVariant = ser::Impossible<Self::Ok, Self::Error>;
-    type SerializeMap = ser::Impossible<Self::Ok, Self::Error>;
-    type SerializeStruct = StructSerializer<'input, 'output, Target>;
+    type SerializeMap = MapSerializer<'output, Target>;
+    type SerializeStruct = StructSerializer<'output, Target>;
     type SerializeStructVariant = ser::Impossible<Self::Ok, Self::Error>;
 
     /// Returns an error.
@@ -279,11 +339,17 @@ where
     }
 
     /// Serializes a map, given length is ignored.
+    ///
+    /// This is also how `#[serde(flatten)]` fields are serialized.
     fn serialize_map(
         self,
         _len: Option<usize>,
     ) -> Result<Self::SerializeMap, Error> {
-        Err(Error::top_level())
+        Ok(MapSerializer {
+            urlencoder: self.urlencoder,
+            options: self.options,
+            key: None,
+        })
     }
 
     /// Serializes a struct, given length is ignored.
@@ -294,6 +360,7 @@ where
     ) -> Result<Self::SerializeStruct, Error> {
         Ok(StructSerializer {
             urlencoder: self.urlencoder,
+            options: self.options,
         })
     }
 
@@ -309,12 +376,11 @@ where
     }
 }
 
-impl<'input, 'output, Target> ser::SerializeStruct
-    for StructSerializer<'input, 'output, Target>
+impl<'output, Target> ser::SerializeStruct for StructSerializer<'output, Target>
 where
-    Target: 'output + UrlEncodedTarget,
+    Target: 'output + Sink,
 {
-    type Ok = &'output mut UrlEncodedSerializer<'input, Target>;
+    type Ok = &'output mut Target;
     type Error = Error;
 
     fn serialize_field<T: ?Sized + ser::Serialize>(
@@ -322,8 +388,55 @@ where
         key: &'static str,
         value: &T,
     ) -> Result<(), Error> {
-        let value_ser = value::ValueSerializer::new(self.urlencoder, key);
-        value.serialize(value_ser)
+        let value_ser =
+            value::ValueSerializer::new(self.urlencoder, key, self.options);
+        value
+            .serialize(value_ser)
+            .map_err(|err| err.in_field(key, None))
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(self.urlencoder)
+    }
+}
+
+/// Map serializer.
+///
+/// Keys must serialize as scalar values, values are serialized like struct
+/// fields.
+pub struct MapSerializer<'output, Target: Sink> {
+    urlencoder: &'output mut Target,
+    options: Options,
+    key: Option<String>,
+}
+
+impl<'output, Target> ser::SerializeMap for MapSerializer<'output, Target>
+where
+    Target: 'output + Sink,
+{
+    type Ok = &'output mut Target;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + ser::Serialize>(
+        &mut self,
+        key: &T,
+    ) -> Result<(), Error> {
+        self.key = Some(value::key_to_string(key, self.options)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + ser::Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Error> {
+        let key = self.key.take().ok_or_else(|| {
+            Error::Custom("tried to serialize a value before its key".into())
+        })?;
+        let value_ser =
+            value::ValueSerializer::new(self.urlencoder, &key, self.options);
+        value
+            .serialize(value_ser)
+            .map_err(|err| err.in_field(&key, None))
     }
 
     fn end(self) -> Result<Self::Ok, Error> {
@@ -332,8 +445,38 @@ where
 }
 
 impl Error {
+    /// Returns the key of the field whose value could not be serialized, if
+    /// any.
+    pub fn key(&self) -> Option<&str> {
+        match *self {
+            Error::Field { ref key, .. } => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Returns the index of the sequence element that could not be
+    /// serialized, if any.
+    pub fn index(&self) -> Option<usize> {
+        match *self {
+            Error::Field { index, .. } => index,
+            _ => None,
+        }
+    }
+
     fn top_level() -> Self {
-        let msg = "top-level serializer supports only structs";
+        let msg = "top-level serializer supports only structs and maps";
         Error::Custom(msg.into())
     }
+
+    /// Attributes this error to the given field, unless it already is.
+    pub(crate) fn in_field(self, key: &str, index: Option<usize>) -> Self {
+        match self {
+            Error::Field { .. } => self,
+            error => Error::Field {
+                key: key.into(),
+                index,
+                error: Box::new(error),
+            },
+        }
+    }
 }
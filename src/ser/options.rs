@@ -0,0 +1,129 @@
+use crate::ser::{Encoder, Encoding, Error, Nesting, SeqStyle, Serializer};
+
+use serde::ser;
+
+/// Options controlling how values are serialized.
+///
+/// ```
+/// use serde_derive::Serialize;
+/// use serde_urlencoded_xrpc::ser::{Encoding, Options};
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     uri: &'static str,
+///     q: &'static str,
+/// }
+///
+/// let params = Params {
+///     uri: "at://did:plc:xyz/app.bsky.feed.post/3k",
+///     q: "hello world",
+/// };
+///
+/// assert_eq!(
+///     Options::new().encoding(Encoding::Rfc3986).to_string(params),
+///     Ok("uri=at://did:plc:xyz/app.bsky.feed.post/3k&q=hello%20world"
+///         .to_owned()));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Options {
+    pub(crate) encoding: Encoding,
+    pub(crate) variant_separator: char,
+    pub(crate) nesting: Option<Nesting>,
+    pub(crate) seq_style: SeqStyle,
+    pub(crate) none_as_empty: bool,
+    pub(crate) xrpc_strict: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            encoding: Encoding::default(),
+            variant_separator: ':',
+            nesting: None,
+            seq_style: SeqStyle::default(),
+            none_as_empty: false,
+            xrpc_strict: false,
+        }
+    }
+}
+
+impl Options {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// How keys and values are percent-encoded.
+    ///
+    /// Defaults to [`Encoding::Form`].
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// The separator written between the name of an enum variant and each of
+    /// its fields, e.g. `top:24h` for `Sort::Top(Window::Day)`.
+    ///
+    /// Defaults to `:`. Fields other than the last one must not contain the
+    /// separator, or they could not be told apart when deserializing.
+    pub fn variant_separator(mut self, separator: char) -> Self {
+        self.variant_separator = separator;
+        self
+    }
+
+    /// Whether struct and map values are serialized as nested keys, such as
+    /// `filter.author` or `filter[author]`, and with which notation.
+    ///
+    /// Defaults to `None`, where struct and map values are an error.
+    /// Sequences of structs and maps are written with the element index in
+    /// the key, e.g. `items.0.name`, except with [`Nesting::Bracket`].
+    pub fn nesting(mut self, nesting: Option<Nesting>) -> Self {
+        self.nesting = nesting;
+        self
+    }
+
+    /// How the elements of sequence values are written.
+    ///
+    /// Defaults to [`SeqStyle::Repeated`]. Fields using one of the
+    /// [`seq_style`] modules override it.
+    ///
+    /// [`seq_style`]: crate::seq_style
+    pub fn seq_style(mut self, style: SeqStyle) -> Self {
+        self.seq_style = style;
+        self
+    }
+
+    /// Whether `None` is written as a key with an empty value, e.g.
+    /// `cursor=`, instead of being omitted.
+    ///
+    /// Defaults to `false`. Does not apply to the elements of
+    /// comma-separated sequences and to enum variant fields.
+    pub fn none_as_empty(mut self, none_as_empty: bool) -> Self {
+        self.none_as_empty = none_as_empty;
+        self
+    }
+
+    /// Whether only the lexicon query parameter types are accepted: booleans,
+    /// integers within the `i64` range, strings, and sequences of those.
+    ///
+    /// Defaults to `false`. When enabled, floats, `char`s, bytes, unit
+    /// structs, enum variants with data and nested structs and maps are
+    /// errors naming the offending field. Unit variants are allowed, as they
+    /// are written as strings, and tuples and fixed-size arrays such as
+    /// `[String; 2]` are written like sequences, each element being checked
+    /// in turn.
+    pub fn xrpc_strict(mut self, strict: bool) -> Self {
+        self.xrpc_strict = strict;
+        self
+    }
+
+    /// Serializes a value into a `String` buffer using these options.
+    pub fn to_string<T: ser::Serialize>(
+        &self,
+        input: T,
+    ) -> Result<String, Error> {
+        let mut encoder = Encoder::new(self.encoding);
+        input.serialize(Serializer::with_options(&mut encoder, *self))?;
+        Ok(encoder.finish())
+    }
+}
@@ -1,52 +1,97 @@
-use crate::ser::Error;
+use crate::ser::{Error, Nesting, Options, SeqStyle, Sink};
 
-use form_urlencoded::Serializer as UrlEncodedSerializer;
-use form_urlencoded::Target as UrlEncodedTarget;
-use serde::ser::{Impossible, Serialize, SerializeSeq};
+use serde::ser::{
+    Serialize, SerializeMap, SerializeSeq, SerializeStruct,
+    SerializeStructVariant, SerializeTuple, SerializeTupleStruct,
+    SerializeTupleVariant,
+};
 use serde::Serializer;
+use std::convert::TryFrom;
 use std::str;
 
-pub struct ValueSerializer<'input, 'key, 'target, Target>
+pub struct ValueSerializer<'key, 'target, Target>
 where
-    Target: UrlEncodedTarget,
+    Target: Sink,
 {
-    urlencoder: &'target mut UrlEncodedSerializer<'input, Target>,
+    urlencoder: &'target mut Target,
     allow_seq: bool,
     key: &'key str,
+    /// The index of this value if it is a sequence element.
+    index: Option<usize>,
+    options: Options,
 }
 
-impl<'input, 'key, 'target, Target>
-    ValueSerializer<'input, 'key, 'target, Target>
+impl<'key, 'target, Target> ValueSerializer<'key, 'target, Target>
 where
-    Target: 'target + UrlEncodedTarget,
+    Target: 'target + Sink,
 {
     pub fn new(
-        urlencoder: &'target mut UrlEncodedSerializer<'input, Target>,
+        urlencoder: &'target mut Target,
         key: &'key str,
+        options: Options,
     ) -> Self {
         ValueSerializer {
             urlencoder,
             allow_seq: true,
             key,
+            index: None,
+            options,
         }
     }
 }
 
-impl<'input, 'key, 'target, Target> Serializer
-    for ValueSerializer<'input, 'key, 'target, Target>
+/// Serializes a map key, which must be a single scalar value.
+pub fn key_to_string<T: ?Sized + Serialize>(
+    key: &T,
+    options: Options,
+) -> Result<String, Error> {
+    scalar_to_string(key, options)?
+        .ok_or_else(|| Error::Custom("map key must not be `None`".into()))
+}
+
+/// Serializes a single scalar value, returning `None` if it is `None`.
+fn scalar_to_string<T: ?Sized + Serialize>(
+    value: &T,
+    options: Options,
+) -> Result<Option<String>, Error> {
+    struct ScalarSink(Option<String>);
+
+    impl Sink for ScalarSink {
+        fn append_pair(&mut self, _key: &str, value: &str) {
+            self.0 = Some(value.to_owned());
+        }
+    }
+
+    let mut sink = ScalarSink(None);
+    value.serialize(ValueSerializer {
+        urlencoder: &mut sink,
+        allow_seq: false,
+        key: "",
+        index: None,
+        options: Options {
+            nesting: None,
+            none_as_empty: false,
+            ..options
+        },
+    })?;
+    Ok(sink.0)
+}
+
+impl<'key, 'target, Target> Serializer
+    for ValueSerializer<'key, 'target, Target>
 where
-    Target: 'target + UrlEncodedTarget,
+    Target: 'target + Sink,
     'target: 'key,
 {
     type Ok = ();
     type Error = Error;
-    type SerializeSeq = ValueSeqSerializer<'input, 'key, 'target, Target>;
-    type SerializeTuple = Impossible<Self::Ok, Error>;
-    type SerializeTupleStruct = Impossible<Self::Ok, Error>;
-    type SerializeTupleVariant = Impossible<Self::Ok, Error>;
-    type SerializeMap = Impossible<Self::Ok, Error>;
-    type SerializeStruct = Impossible<Self::Ok, Error>;
-    type SerializeStructVariant = Impossible<Self::Ok, Error>;
+    type SerializeSeq = ValueSeqSerializer<'key, 'target, Target>;
+    type SerializeTuple = ValueSeqSerializer<'key, 'target, Target>;
+    type SerializeTupleStruct = ValueSeqSerializer<'key, 'target, Target>;
+    type SerializeTupleVariant = VariantSerializer<'key, 'target, Target>;
+    type SerializeMap = NestedSerializer<'target, Target>;
+    type SerializeStruct = NestedSerializer<'target, Target>;
+    type SerializeStructVariant = VariantSerializer<'key, 'target, Target>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Error> {
         self.serialize_str(if v { "true" } else { "false" })
@@ -93,14 +138,17 @@ where
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Error> {
+        self.check_lexicon_type("f32")?;
         self.serialize_floating(v)
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Error> {
+        self.check_lexicon_type("f64")?;
         self.serialize_floating(v)
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok, Error> {
+        self.check_lexicon_type("char")?;
         self.collect_str(&v)
     }
 
@@ -110,6 +158,7 @@ where
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Error> {
+        self.check_lexicon_type("bytes")?;
         match str::from_utf8(value) {
             Ok(value) => self.serialize_str(value),
             Err(err) => Err(Error::Utf8(err)),
@@ -124,6 +173,7 @@ where
         self,
         name: &'static str,
     ) -> Result<Self::Ok, Error> {
+        self.check_lexicon_type("unit struct")?;
         self.serialize_str(name)
     }
 
@@ -136,11 +186,16 @@ where
         self.serialize_str(variant)
     }
 
+    /// Serializes the inner value, using the sequence style requested by a
+    /// `seq_style` module if any.
     fn serialize_newtype_struct<T: ?Sized + Serialize>(
-        self,
-        _name: &'static str,
+        mut self,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Error> {
+        if let Some(style) = SeqStyle::from_newtype_name(name) {
+            self.options.seq_style = style;
+        }
         value.serialize(self)
     }
 
@@ -148,13 +203,19 @@ where
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
+        variant: &'static str,
+        value: &T,
     ) -> Result<Self::Ok, Error> {
-        Err(self.unsupported("newtype variant"))
+        self.check_lexicon_type("enum variant with data")?;
+        let mut variant = VariantSerializer::new(self, variant, 1);
+        SerializeTupleVariant::serialize_field(&mut variant, value)?;
+        SerializeTupleVariant::end(variant)
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Error> {
+        if self.options.none_as_empty {
+            self.urlencoder.append_pair(self.key, "");
+        }
         Ok(())
     }
 
@@ -170,7 +231,7 @@ where
         _len: Option<usize>,
     ) -> Result<Self::SerializeSeq, Error> {
         if self.allow_seq {
-            Ok(ValueSeqSerializer { inner: self })
+            Ok(ValueSeqSerializer::new(self))
         } else {
             Err(self.unsupported("sequence"))
         }
@@ -180,32 +241,41 @@ where
         self,
         _len: usize,
     ) -> Result<Self::SerializeTuple, Error> {
-        Err(self.unsupported("tuple"))
+        if self.allow_seq {
+            Ok(ValueSeqSerializer::new(self))
+        } else {
+            Err(self.unsupported("tuple"))
+        }
     }
 
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
         _len: usize,
-    ) -> Result<Self::SerializeTuple, Error> {
-        Err(self.unsupported("tuple struct"))
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        if self.allow_seq {
+            Ok(ValueSeqSerializer::new(self))
+        } else {
+            Err(self.unsupported("tuple struct"))
+        }
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant, Error> {
-        Err(self.unsupported("tuple variant"))
+        self.check_lexicon_type("enum variant with data")?;
+        Ok(VariantSerializer::new(self, variant, len))
     }
 
     fn serialize_map(
         self,
         _len: Option<usize>,
     ) -> Result<Self::SerializeMap, Error> {
-        Err(self.unsupported("map"))
+        self.serialize_nested("map")
     }
 
     fn serialize_struct(
@@ -213,31 +283,37 @@ where
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Error> {
-        Err(self.unsupported("struct"))
+        self.serialize_nested("struct")
     }
 
     fn serialize_struct_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant, Error> {
-        Err(self.unsupported("struct variant"))
+        self.check_lexicon_type("enum variant with data")?;
+        Ok(VariantSerializer::new(self, variant, len))
     }
 }
 
-impl<'input, 'key, 'target, Target>
-    ValueSerializer<'input, 'key, 'target, Target>
+impl<'key, 'target, Target> ValueSerializer<'key, 'target, Target>
 where
-    Target: UrlEncodedTarget,
+    Target: Sink,
 {
     fn serialize_integer<I>(self, value: I) -> Result<(), Error>
     where
         I: itoa::Integer,
+        i64: TryFrom<I>,
     {
         let mut buf = itoa::Buffer::new();
         let part = buf.format(value);
+        if self.options.xrpc_strict && i64::try_from(value).is_err() {
+            let msg =
+                format!("integer {} is out of range for the lexicon", part);
+            return Err(Error::Custom(msg.into()));
+        }
         Serializer::serialize_str(self, part)
     }
 
@@ -250,20 +326,110 @@ where
         Serializer::serialize_str(self, part)
     }
 
+    fn serialize_nested(
+        self,
+        type_str: &'static str,
+    ) -> Result<NestedSerializer<'target, Target>, Error> {
+        self.check_lexicon_type(type_str)?;
+        let nesting = match self.options.nesting {
+            Some(nesting) => nesting,
+            None => return Err(self.unsupported(type_str)),
+        };
+        let mut prefix = self.key.to_owned();
+        if let Some(index) = self.index {
+            nesting.push_index(&mut prefix, index);
+        }
+        Ok(NestedSerializer {
+            urlencoder: self.urlencoder,
+            prefix,
+            nesting,
+            options: self.options,
+            key: None,
+        })
+    }
+
+    /// Rejects a type that is not a lexicon query parameter type in XRPC
+    /// strict mode.
+    fn check_lexicon_type(&self, type_str: &'static str) -> Result<(), Error> {
+        if self.options.xrpc_strict {
+            let msg =
+                format!("{} is not a lexicon query parameter type", type_str);
+            return Err(Error::Custom(msg.into()));
+        }
+        Ok(())
+    }
+
     fn unsupported(self, type_str: &'static str) -> Error {
         Error::Custom(format!("unsupported value type: {type_str}").into())
     }
 }
 
-/// Sequence value serializer.
-pub struct ValueSeqSerializer<'input, 'key, 'target, Target: UrlEncodedTarget> {
-    inner: ValueSerializer<'input, 'key, 'target, Target>,
+/// Sequence value serializer, writing elements in the configured
+/// `SeqStyle`.
+///
+/// Also used for tuples and tuple structs, which are serialized as
+/// sequences of their fields.
+pub struct ValueSeqSerializer<'key, 'target, Target: Sink> {
+    inner: ValueSerializer<'key, 'target, Target>,
+    index: usize,
+    /// The elements written so far with `SeqStyle::CommaSeparated`.
+    joined: Option<String>,
+}
+
+impl<'key, 'target, Target> ValueSeqSerializer<'key, 'target, Target>
+where
+    Target: Sink,
+{
+    fn new(inner: ValueSerializer<'key, 'target, Target>) -> Self {
+        ValueSeqSerializer {
+            inner,
+            index: 0,
+            joined: None,
+        }
+    }
+
+    fn serialize_element_with_key<T: ?Sized + Serialize>(
+        &mut self,
+        key: &str,
+        index: Option<usize>,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(ValueSerializer {
+            urlencoder: self.inner.urlencoder,
+            allow_seq: false,
+            key,
+            index,
+            options: self.inner.options,
+        })
+    }
+
+    fn push_joined<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Error> {
+        let element = match scalar_to_string(value, self.inner.options)? {
+            Some(element) => element,
+            None => return Ok(()),
+        };
+        if element.contains(',') {
+            let msg = format!("element `{}` contains a comma", element);
+            return Err(Error::Custom(msg.into()));
+        }
+        match self.joined {
+            Some(ref mut joined) => {
+                joined.push(',');
+                joined.push_str(&element);
+            }
+            None => self.joined = Some(element),
+        }
+        Ok(())
+    }
 }
 
-impl<'input, 'output, 'target, Target> SerializeSeq
-    for ValueSeqSerializer<'input, 'output, 'target, Target>
+impl<'output, 'target, Target> SerializeSeq
+    for ValueSeqSerializer<'output, 'target, Target>
 where
-    Target: 'output + UrlEncodedTarget,
+    Target: 'output + Sink,
 {
     type Ok = ();
     type Error = Error;
@@ -272,13 +438,240 @@ where
         &mut self,
         value: &T,
     ) -> Result<Self::Ok, Error> {
-        let ser = ValueSerializer {
-            urlencoder: self.inner.urlencoder,
-            allow_seq: false,
-            key: self.inner.key,
+        let index = self.index;
+        self.index += 1;
+        let key = self.inner.key;
+        let result = match self.inner.options.seq_style {
+            SeqStyle::Repeated => {
+                self.serialize_element_with_key(key, Some(index), value)
+            }
+            SeqStyle::CommaSeparated => self.push_joined(value),
+            SeqStyle::Bracketed => {
+                let key = format!("{}[]", key);
+                self.serialize_element_with_key(&key, None, value)
+            }
+            SeqStyle::Indexed => {
+                let key = format!("{}[{}]", key, index);
+                self.serialize_element_with_key(&key, None, value)
+            }
         };
+        result.map_err(|err| err.in_field(key, Some(index)))
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        if let Some(joined) = self.joined {
+            self.inner.urlencoder.append_pair(self.inner.key, &joined);
+        }
+        Ok(())
+    }
+}
+
+impl<'output, 'target, Target> SerializeTuple
+    for ValueSeqSerializer<'output, 'target, Target>
+where
+    Target: 'output + Sink,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl<'output, 'target, Target> SerializeTupleStruct
+    for ValueSeqSerializer<'output, 'target, Target>
+where
+    Target: 'output + Sink,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+/// Serializes an enum variant with data as a single value: the variant name
+/// followed by each of its fields, every one preceded by the variant
+/// separator, e.g. `top:24h`.
+///
+/// Struct variants are written like tuple variants, without field names.
+pub struct VariantSerializer<'key, 'target, Target: Sink> {
+    inner: ValueSerializer<'key, 'target, Target>,
+    value: String,
+    len: usize,
+    index: usize,
+}
+
+impl<'key, 'target, Target> VariantSerializer<'key, 'target, Target>
+where
+    Target: Sink,
+{
+    fn new(
+        inner: ValueSerializer<'key, 'target, Target>,
+        variant: &'static str,
+        len: usize,
+    ) -> Self {
+        VariantSerializer {
+            inner,
+            value: variant.to_owned(),
+            len,
+            index: 0,
+        }
+    }
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), Error> {
+        let separator = self.inner.options.variant_separator;
+        let field =
+            scalar_to_string(value, self.inner.options)?.ok_or_else(|| {
+                Error::Custom("variant field must not be `None`".into())
+            })?;
+        self.index += 1;
+        if self.index < self.len && field.contains(separator) {
+            let msg = format!(
+                "variant field `{}` contains the separator `{}`",
+                field, separator
+            );
+            return Err(Error::Custom(msg.into()));
+        }
+        self.value.push(separator);
+        self.value.push_str(&field);
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Serializer::serialize_str(self.inner, &self.value)
+    }
+}
+
+impl<'key, 'target, Target> SerializeTupleVariant
+    for VariantSerializer<'key, 'target, Target>
+where
+    Target: Sink,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        VariantSerializer::serialize_field(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        VariantSerializer::end(self)
+    }
+}
+
+impl<'key, 'target, Target> SerializeStructVariant
+    for VariantSerializer<'key, 'target, Target>
+where
+    Target: Sink,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        VariantSerializer::serialize_field(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        VariantSerializer::end(self)
+    }
+}
+
+/// Serializes a struct or map value as a key for each of its fields, nested
+/// under the key of the value.
+pub struct NestedSerializer<'target, Target: Sink> {
+    urlencoder: &'target mut Target,
+    prefix: String,
+    nesting: Nesting,
+    options: Options,
+    key: Option<String>,
+}
+
+impl<'target, Target> NestedSerializer<'target, Target>
+where
+    Target: Sink,
+{
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        field: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let mut key = self.prefix.clone();
+        self.nesting.push_field(&mut key, field);
+        let ser = ValueSerializer::new(self.urlencoder, &key, self.options);
+        value.serialize(ser).map_err(|err| err.in_field(&key, None))
+    }
+}
+
+impl<'target, Target> SerializeStruct for NestedSerializer<'target, Target>
+where
+    Target: Sink,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        NestedSerializer::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(())
+    }
+}
+
+impl<'target, Target> SerializeMap for NestedSerializer<'target, Target>
+where
+    Target: Sink,
+{
+    type Ok = ();
+    type Error = Error;
 
-        value.serialize(ser)
+    fn serialize_key<T: ?Sized + Serialize>(
+        &mut self,
+        key: &T,
+    ) -> Result<Self::Ok, Error> {
+        self.key = Some(key_to_string(key, self.options)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        let key = self.key.take().ok_or_else(|| {
+            Error::Custom("tried to serialize a value before its key".into())
+        })?;
+        NestedSerializer::serialize_field(self, &key, value)
     }
 
     fn end(self) -> Result<Self::Ok, Error> {
@@ -0,0 +1,232 @@
+//! Modules for `#[serde(with = "...")]` that encode a field as a single
+//! string value.
+//!
+//! Unlike the [`seq_style`](crate::seq_style) modules, these go through
+//! `serialize_str` and `deserialize_str`, so they work with any format and
+//! for map values and enum fields as well as struct fields.
+//!
+//! ```
+//! use serde_derive::{Deserialize, Serialize};
+//! use serde_urlencoded_xrpc::with;
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Params {
+//!     #[serde(with = "with::comma_joined")]
+//!     langs: Vec<String>,
+//!     #[serde(with = "with::display_fromstr")]
+//!     since: std::net::Ipv4Addr,
+//! }
+//!
+//! let params = Params {
+//!     langs: vec!["en".to_owned(), "fr".to_owned()],
+//!     since: "127.0.0.1".parse().unwrap(),
+//! };
+//! let query = "langs=en%2Cfr&since=127.0.0.1";
+//! assert_eq!(serde_urlencoded_xrpc::to_string(&params), Ok(query.to_owned()));
+//! assert_eq!(serde_urlencoded_xrpc::from_str(query), Ok(params));
+//! ```
+
+macro_rules! joined_module {
+    ($(#[$attr:meta])* $module:ident, $separator:literal) => {
+        $(#[$attr])*
+        ///
+        /// An empty string is an empty collection, so serializing a single
+        /// empty element is an error, as is serializing an element that
+        /// contains the separator. Elements are written with `Display` and
+        /// read with `FromStr`.
+        pub mod $module {
+            use serde::de::{self, Deserializer, Visitor};
+            use serde::ser::{Error as _, Serializer};
+            use std::fmt::{self, Display};
+            use std::iter::FromIterator;
+            use std::marker::PhantomData;
+            use std::str::FromStr;
+
+            const SEPARATOR: char = $separator;
+
+            /// Serializes the elements of `value` as a single string.
+            pub fn serialize<'a, T, S>(
+                value: &'a T,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                T: ?Sized,
+                &'a T: IntoIterator,
+                <&'a T as IntoIterator>::Item: Display,
+                S: Serializer,
+            {
+                let mut joined = String::new();
+                let mut len = 0;
+                for element in value {
+                    let element = element.to_string();
+                    if element.contains(SEPARATOR) {
+                        return Err(S::Error::custom(format_args!(
+                            "element `{}` contains the separator `{}`",
+                            element, SEPARATOR
+                        )));
+                    }
+                    if len > 0 {
+                        joined.push(SEPARATOR);
+                    }
+                    joined.push_str(&element);
+                    len += 1;
+                }
+                if len == 1 && joined.is_empty() {
+                    return Err(S::Error::custom(
+                        "a single empty element cannot be told apart from \
+                         an empty collection",
+                    ));
+                }
+                serializer.serialize_str(&joined)
+            }
+
+            /// Deserializes a collection from a single string.
+            pub fn deserialize<'de, T, D>(
+                deserializer: D,
+            ) -> Result<T, D::Error>
+            where
+                T: IntoIterator + FromIterator<<T as IntoIterator>::Item>,
+                T::Item: FromStr,
+                <T::Item as FromStr>::Err: Display,
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_str(SeparatedVisitor(PhantomData))
+            }
+
+            struct SeparatedVisitor<T>(PhantomData<T>);
+
+            impl<'de, T> Visitor<'de> for SeparatedVisitor<T>
+            where
+                T: IntoIterator + FromIterator<<T as IntoIterator>::Item>,
+                T::Item: FromStr,
+                <T::Item as FromStr>::Err: Display,
+            {
+                type Value = T;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    write!(f, "a list separated by `{}`", SEPARATOR)
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+                    if v.is_empty() {
+                        return Ok(T::from_iter(None));
+                    }
+                    v.split(SEPARATOR)
+                        .map(|element| element.parse().map_err(E::custom))
+                        .collect()
+                }
+            }
+        }
+    };
+}
+
+joined_module! {
+    /// Joins a collection into a single comma-separated value: `tags=a,b`.
+    ///
+    /// The field is a string in every format, e.g. `"a,b"` in JSON. To only
+    /// change how this crate writes the sequence, with elements going
+    /// through their `Serialize` and `Deserialize` impls, use
+    /// [`seq_style::comma_separated`](crate::seq_style::comma_separated)
+    /// instead.
+    comma_joined, ','
+}
+
+joined_module! {
+    /// Joins a collection into a single space-separated value: `scope=a+b`.
+    space_joined, ' '
+}
+
+/// Encodes a value as a string with its `Display` and `FromStr` impls.
+///
+/// Useful for types that do not implement `Serialize` and `Deserialize`, or
+/// whose implementations do not produce a string.
+pub mod display_fromstr {
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::ser::Serializer;
+    use std::fmt::{self, Display};
+    use std::marker::PhantomData;
+    use std::str::FromStr;
+
+    /// Serializes `value` with its `Display` impl.
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Display,
+        S: Serializer,
+    {
+        serializer.collect_str(value)
+    }
+
+    /// Deserializes a value with its `FromStr` impl.
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(FromStrVisitor(PhantomData))
+    }
+
+    struct FromStrVisitor<T>(PhantomData<T>);
+
+    impl<'de, T> Visitor<'de> for FromStrVisitor<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        type Value = T;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a string")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            v.parse().map_err(E::custom)
+        }
+    }
+}
+
+/// Encodes a value as a single compact JSON value.
+///
+/// JSON syntax errors and type mismatches are reported as deserialization
+/// errors of the field.
+#[cfg(feature = "json")]
+pub mod json {
+    use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+    use serde::ser::{Error as _, Serialize, Serializer};
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    /// Serializes `value` as compact JSON.
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Serialize,
+        S: Serializer,
+    {
+        let json = serde_json::to_string(value).map_err(S::Error::custom)?;
+        serializer.serialize_str(&json)
+    }
+
+    /// Deserializes a value from JSON.
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: DeserializeOwned,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(JsonVisitor(PhantomData))
+    }
+
+    struct JsonVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: DeserializeOwned> Visitor<'de> for JsonVisitor<T> {
+        type Value = T;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a JSON value")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            serde_json::from_str(v)
+                .map_err(|err| E::custom(format_args!("invalid JSON: {}", err)))
+        }
+    }
+}
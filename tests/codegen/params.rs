@@ -0,0 +1,51 @@
+// Generated from lexicon documents by serde_urlencoded_xrpc. Do not edit.
+
+pub mod app {
+    pub mod bsky {
+        pub mod actor {
+            /// Parameters of the `app.bsky.actor.getProfiles` query.
+            #[derive(Clone, Debug, PartialEq)]
+            #[derive(::serde_derive::Serialize, ::serde_derive::Deserialize)]
+            pub struct GetProfilesParams {
+                pub actors: Vec<::serde_urlencoded_xrpc::formats::AtIdentifier>,
+            }
+        }
+
+        pub mod feed {
+            /// Parameters of the `app.bsky.feed.getAuthorFeed` query.
+            ///
+            /// Get a view of an actor's 'author feed'.
+            #[derive(Clone, Debug, PartialEq)]
+            #[derive(::serde_derive::Serialize, ::serde_derive::Deserialize)]
+            pub struct GetAuthorFeedParams {
+                pub actor: ::serde_urlencoded_xrpc::formats::AtIdentifier,
+                pub cursor: Option<String>,
+
+                /// Combinations of post/repost types to include in response.
+                ///
+                /// Defaults to `posts_with_replies`.
+                pub filter: Option<String>,
+
+                /// Defaults to `false`.
+                #[serde(rename = "includePins")]
+                pub include_pins: Option<bool>,
+
+                /// Defaults to `50`.
+                pub limit: Option<i64>,
+            }
+
+            /// Parameters of the `app.bsky.feed.searchPosts` query.
+            #[derive(Clone, Debug, PartialEq)]
+            #[derive(::serde_derive::Serialize, ::serde_derive::Deserialize)]
+            pub struct SearchPostsParams {
+                pub lang: Option<::serde_urlencoded_xrpc::formats::Language>,
+                pub q: String,
+
+                /// Defaults to `latest`.
+                pub sort: Option<String>,
+                #[serde(default)]
+                pub tag: Vec<String>,
+            }
+        }
+    }
+}
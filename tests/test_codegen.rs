@@ -0,0 +1,124 @@
+#![cfg(feature = "lexicon")]
+
+use serde_urlencoded_xrpc::de::ErrorKind;
+use serde_urlencoded_xrpc::lexicon::codegen::Options;
+use serde_urlencoded_xrpc::lexicon::Lexicons;
+
+#[allow(dead_code)]
+mod params {
+    include!("codegen/params.rs");
+}
+
+use self::params::app::bsky::actor::GetProfilesParams;
+use self::params::app::bsky::feed::{GetAuthorFeedParams, SearchPostsParams};
+
+fn round_trip<T>(params: &T, query: &str)
+where
+    T: serde::Serialize + serde::de::DeserializeOwned + std::fmt::Debug,
+    T: PartialEq,
+{
+    assert_eq!(serde_urlencoded_xrpc::to_string(params).unwrap(), query);
+    assert_eq!(
+        &serde_urlencoded_xrpc::from_str::<T>(query).unwrap(),
+        params
+    );
+}
+
+#[test]
+fn codegen_matches_checked_in_output() {
+    let lexicons = Lexicons::load_dir(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/lexicons",
+    ))
+    .unwrap();
+    let code = Options::new()
+        .serde_path("::serde_derive")
+        .generate(&lexicons);
+    assert_eq!(code, include_str!("codegen/params.rs"));
+}
+
+#[test]
+fn codegen_round_trip() {
+    round_trip(
+        &GetAuthorFeedParams {
+            actor: "did:plc:abc123".parse().unwrap(),
+            cursor: None,
+            filter: Some("posts_no_replies".to_owned()),
+            include_pins: Some(true),
+            limit: Some(30),
+        },
+        "actor=did%3Aplc%3Aabc123&filter=posts_no_replies&includePins=true\
+         &limit=30",
+    );
+    round_trip(
+        &GetProfilesParams {
+            actors: vec![
+                "alice.test".parse().unwrap(),
+                "did:plc:abc123".parse().unwrap(),
+            ],
+        },
+        "actors=alice.test&actors=did%3Aplc%3Aabc123",
+    );
+    round_trip(
+        &SearchPostsParams {
+            lang: Some("pt-BR".parse().unwrap()),
+            q: "rust".to_owned(),
+            sort: None,
+            tag: vec![],
+        },
+        "lang=pt-BR&q=rust",
+    );
+}
+
+#[test]
+fn codegen_required_array_is_not_defaulted() {
+    let err =
+        serde_urlencoded_xrpc::from_str::<GetProfilesParams>("").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingField);
+    assert_eq!(err.key(), Some("actors"));
+
+    let params: SearchPostsParams =
+        serde_urlencoded_xrpc::from_str("q=rust").unwrap();
+    assert!(params.tag.is_empty());
+}
+
+#[test]
+fn codegen_rejects_invalid_formats() {
+    let err =
+        serde_urlencoded_xrpc::from_str::<GetAuthorFeedParams>("actor=-bad-")
+            .unwrap_err();
+    assert!(err.to_string().contains("invalid at-identifier"), "{}", err);
+}
+
+#[test]
+fn codegen_paths() {
+    let mut lexicons = Lexicons::new();
+    lexicons
+        .add_json(
+            r#"{
+                "id": "com.example.getThing",
+                "defs": {
+                    "main": {
+                        "type": "query",
+                        "parameters": {
+                            "type": "params",
+                            "required": ["type"],
+                            "properties": {
+                                "type": { "type": "string" },
+                                "repoDID": { "type": "string", "format": "did" }
+                            }
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+    let code = Options::new().crate_path("crate::xrpc").generate(&lexicons);
+    assert!(
+        code.contains("#[derive(::serde::Serialize, ::serde::Deserialize)]")
+    );
+    assert!(code.contains("pub struct GetThingParams {"));
+    assert!(code.contains("    pub r#type: String,\n"));
+    assert!(code.contains("#[serde(rename = \"repoDID\")]"));
+    assert!(code.contains("pub repo_did: Option<crate::xrpc::formats::Did>,"));
+}
@@ -1,4 +1,14 @@
 use serde_derive::Deserialize;
+use serde_urlencoded_xrpc::bounded::Bounded;
+use serde_urlencoded_xrpc::de::{
+    buffered, DuplicateKeys, ErrorKind, Limit, Nesting, Options, SeqStyle,
+};
+use serde_urlencoded_xrpc::open_enum::OpenEnum;
+use serde_urlencoded_xrpc::query_map::QueryMap;
+use serde_urlencoded_xrpc::query_value::QueryValue;
+use serde_urlencoded_xrpc::seq_style;
+use serde_urlencoded_xrpc::with;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 
 #[derive(Deserialize, Debug, PartialEq)]
 struct NewType<T>(T);
@@ -106,3 +116,1477 @@ fn deserialize_128bit() {
     let q = format!("min={}", i128::MIN);
     assert_eq!(serde_urlencoded_xrpc::from_str(&q), Ok(result));
 }
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct ListStruct<T> {
+    x: Vec<T>,
+    y: Vec<T>,
+}
+
+#[test]
+fn deserialize_struct_vec() {
+    let result = ListStruct {
+        x: vec![1, 2, 3],
+        y: vec![4, 5, 6],
+    };
+
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str("x=1&x=2&x=3&y=4&y=5&y=6"),
+        Ok(result)
+    );
+}
+
+#[test]
+fn deserialize_struct_vec_interleaved() {
+    let result = ListStruct {
+        x: vec![1, 3],
+        y: vec![2],
+    };
+
+    assert_eq!(serde_urlencoded_xrpc::from_str("x=1&y=2&x=3"), Ok(result));
+}
+
+#[test]
+fn deserialize_struct_vec_single() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        uris: Vec<String>,
+        cursor: Option<String>,
+    }
+
+    let result = Params {
+        uris: vec!["at://did:plc:xyz".to_owned()],
+        cursor: None,
+    };
+
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str("uris=at%3A%2F%2Fdid%3Aplc%3Axyz"),
+        Ok(result)
+    );
+}
+
+#[test]
+fn deserialize_duplicate_scalar() {
+    #[derive(Deserialize, Debug)]
+    struct Params {
+        #[allow(dead_code)]
+        limit: u32,
+    }
+
+    let err = serde_urlencoded_xrpc::from_str::<Params>("limit=1&limit=2")
+        .unwrap_err();
+    assert_eq!(err.to_string(), "duplicate field `limit`");
+}
+
+#[test]
+fn deserialize_ignored_repeated_key() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        limit: u32,
+    }
+
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str("limit=1&other=a&other=b"),
+        Ok(Params { limit: 1 })
+    );
+}
+
+#[test]
+fn deserialize_pairs_keep_order() {
+    let result = vec![("a", 1), ("b", 2), ("a", 3)];
+
+    assert_eq!(serde_urlencoded_xrpc::from_str("a=1&b=2&a=3"), Ok(result));
+}
+
+#[test]
+fn deserialize_struct_collections() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        hash: HashSet<String>,
+        btree: BTreeSet<u32>,
+        deque: VecDeque<bool>,
+        array: [String; 2],
+        tuple: (u32, bool),
+    }
+
+    let result = Params {
+        hash: vec!["a".to_owned()].into_iter().collect(),
+        btree: vec![1, 2].into_iter().collect(),
+        deque: vec![true, false].into_iter().collect(),
+        array: ["x".to_owned(), "y".to_owned()],
+        tuple: (3, true),
+    };
+
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str(
+            "hash=a&hash=a&btree=2&btree=1&deque=true&deque=false\
+             &array=x&array=y&tuple=3&tuple=true"
+        ),
+        Ok(result)
+    );
+}
+
+#[test]
+fn deserialize_array_length_mismatch() {
+    #[derive(Deserialize, Debug)]
+    struct Params {
+        #[allow(dead_code)]
+        array: [u32; 2],
+    }
+
+    let err = serde_urlencoded_xrpc::from_str::<Params>("array=1").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field `array`: invalid length 1, expected an array of length 2"
+    );
+
+    let err =
+        serde_urlencoded_xrpc::from_str::<Params>("array=1&array=2&array=3")
+            .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field `array`: invalid length 3, expected 2 elements in sequence"
+    );
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Params {
+    limit: u32,
+    reverse: Option<bool>,
+    uris: Vec<u32>,
+}
+
+#[test]
+fn deserialize_error_invalid_number() {
+    let err = serde_urlencoded_xrpc::from_str::<Params>("uris=1&limit=abc")
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+    assert_eq!(err.key(), Some("limit"));
+    assert_eq!(err.value(), Some("abc"));
+    assert_eq!(err.index(), Some(1));
+    assert_eq!(
+        err.to_string(),
+        "field `limit`: invalid digit found in string"
+    );
+}
+
+#[test]
+fn deserialize_error_invalid_bool() {
+    let err =
+        serde_urlencoded_xrpc::from_str::<Params>("limit=1&reverse=yes&uris=1")
+            .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidBool);
+    assert_eq!(err.key(), Some("reverse"));
+    assert_eq!(err.value(), Some("yes"));
+    assert_eq!(err.index(), Some(1));
+}
+
+#[test]
+fn deserialize_error_sequence_element() {
+    let err =
+        serde_urlencoded_xrpc::from_str::<Params>("uris=1&limit=1&uris=x")
+            .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+    assert_eq!(err.key(), Some("uris"));
+    assert_eq!(err.value(), Some("x"));
+    assert_eq!(err.index(), Some(2));
+}
+
+#[test]
+fn deserialize_error_fields() {
+    let err = serde_urlencoded_xrpc::from_str::<Params>("uris=1&limit=1&x=1")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnknownField);
+    assert_eq!(err.key(), Some("x"));
+
+    let err = serde_urlencoded_xrpc::from_str::<Params>("uris=1").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingField);
+    assert_eq!(err.key(), Some("limit"));
+    assert_eq!(err.to_string(), "missing field `limit`");
+
+    let err =
+        serde_urlencoded_xrpc::from_str::<Params>("uris=1&limit=1&limit=2")
+            .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicateField);
+    assert_eq!(err.key(), Some("limit"));
+    assert_eq!(err.index(), Some(2));
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Listing {
+    limit: u32,
+    uris: Option<Vec<u32>>,
+}
+
+#[test]
+fn deserialize_options_duplicate_keys() {
+    let input = "limit=1&uris=1&limit=2&uris=2&limit=3";
+
+    let err = Options::new().from_str::<Listing>(input).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicateField);
+
+    assert_eq!(
+        Options::new()
+            .duplicate_keys(DuplicateKeys::First)
+            .from_str(input),
+        Ok(Listing {
+            limit: 1,
+            uris: Some(vec![1, 2]),
+        })
+    );
+    assert_eq!(
+        Options::new()
+            .duplicate_keys(DuplicateKeys::Last)
+            .from_str(input),
+        Ok(Listing {
+            limit: 3,
+            uris: Some(vec![1, 2]),
+        })
+    );
+}
+
+#[test]
+fn deserialize_map_duplicate_keys() {
+    let input = "a=1&b=2&a=3";
+
+    let map: HashMap<String, String> =
+        serde_urlencoded_xrpc::from_str(input).unwrap();
+    assert_eq!(map["a"], "3");
+
+    let map: HashMap<String, String> = Options::new()
+        .duplicate_keys(DuplicateKeys::First)
+        .from_str(input)
+        .unwrap();
+    assert_eq!(map["a"], "1");
+
+    let err = Options::new()
+        .duplicate_keys(DuplicateKeys::Reject)
+        .from_str::<HashMap<String, String>>(input)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicateField);
+}
+
+#[test]
+fn deserialize_options_deny_unknown_fields() {
+    let input = "limit=1&cursor=abc";
+
+    assert_eq!(
+        Options::new().from_str(input),
+        Ok(Listing {
+            limit: 1,
+            uris: None,
+        })
+    );
+
+    let err = serde_urlencoded_xrpc::Deserializer::builder()
+        .deny_unknown_fields(true)
+        .from_str::<Listing>(input)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnknownField);
+    assert_eq!(err.key(), Some("cursor"));
+    assert_eq!(err.index(), Some(1));
+}
+
+#[test]
+fn deserialize_options_limits() {
+    fn limit_of(err: serde_urlencoded_xrpc::de::Error) -> Limit {
+        match err.kind() {
+            ErrorKind::LimitExceeded(limit) => limit,
+            kind => panic!("unexpected error kind: {:?}", kind),
+        }
+    }
+
+    let input = "limit=1&uris=1&uris=2&uris=3";
+    assert!(Options::new()
+        .max_input_len(input.len())
+        .max_pairs(4)
+        .max_key_len(5)
+        .max_value_len(1)
+        .max_values_per_key(3)
+        .from_str::<Listing>(input)
+        .is_ok());
+
+    let err = Options::new()
+        .max_input_len(10)
+        .from_str::<Listing>(input)
+        .unwrap_err();
+    assert_eq!(limit_of(err), Limit::InputLength);
+
+    let err = Options::new()
+        .max_input_len(10)
+        .from_reader::<Listing, _>(input.as_bytes())
+        .unwrap_err();
+    assert_eq!(limit_of(err), Limit::InputLength);
+
+    let err = Options::new()
+        .max_pairs(3)
+        .from_str::<Vec<(String, u32)>>(input)
+        .unwrap_err();
+    assert_eq!(err.index(), Some(3));
+    assert_eq!(limit_of(err), Limit::Pairs);
+
+    let err = Options::new()
+        .max_key_len(4)
+        .from_str::<Listing>(input)
+        .unwrap_err();
+    assert_eq!(limit_of(err), Limit::KeyLength);
+
+    let err = Options::new()
+        .max_value_len(1)
+        .from_str::<Listing>("limit=10")
+        .unwrap_err();
+    assert_eq!(err.key(), Some("limit"));
+    assert_eq!(limit_of(err), Limit::ValueLength);
+
+    let err = Options::new()
+        .max_values_per_key(2)
+        .from_str::<Listing>(input)
+        .unwrap_err();
+    assert_eq!(err.key(), Some("uris"));
+    assert_eq!(err.index(), Some(3));
+    assert_eq!(limit_of(err), Limit::ValuesPerKey);
+}
+
+#[test]
+fn deserialize_strict_decoding() {
+    let strict = Options::new().strict_decoding(true);
+
+    let pairs: Vec<(String, String)> =
+        strict.from_str("a=comt%C3%A9&b=x+y&c=%2B").unwrap();
+    assert_eq!(pairs[0].1, "comté");
+    assert_eq!(pairs[1].1, "x y");
+    assert_eq!(pairs[2].1, "+");
+
+    let lenient: Vec<(String, String)> =
+        Options::new().from_str("a=%zz&b=%FF").unwrap();
+    assert_eq!(lenient[0].1, "%zz");
+    assert_eq!(lenient[1].1, "\u{FFFD}");
+
+    let err = strict
+        .from_str::<Vec<(String, String)>>("a=1&b=%zz")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidPercentEncoding);
+    assert_eq!(err.offset(), Some(6));
+    assert_eq!(err.index(), Some(1));
+
+    let err = strict
+        .from_str::<Vec<(String, String)>>("a=%4")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidPercentEncoding);
+    assert_eq!(err.offset(), Some(2));
+
+    let err = strict
+        .from_str::<Vec<(String, String)>>("key=ab%C3%A9%FF")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidUtf8);
+    assert_eq!(err.offset(), Some(12));
+
+    let err = strict
+        .from_bytes::<Vec<(String, String)>>(b"a=b&\xff=c")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidUtf8);
+    assert_eq!(err.offset(), Some(4));
+}
+
+#[test]
+fn deserialize_plus_as_space() {
+    let pairs: Vec<(String, String)> =
+        Options::new().from_str("q=a+b%2Bc").unwrap();
+    assert_eq!(pairs[0].1, "a b+c");
+
+    let pairs: Vec<(String, String)> = Options::new()
+        .plus_as_space(false)
+        .from_str("q=a+b%20c")
+        .unwrap();
+    assert_eq!(pairs[0].1, "a+b c");
+
+    let pairs: Vec<(String, String)> = Options::new()
+        .plus_as_space(false)
+        .from_str("q=%zz&r=%FF")
+        .unwrap();
+    assert_eq!(pairs[0].1, "%zz");
+    assert_eq!(pairs[1].1, "\u{FFFD}");
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Pagination {
+    limit: Option<u32>,
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct FlattenParams {
+    actor: String,
+    #[serde(flatten)]
+    pagination: Pagination,
+}
+
+#[test]
+fn deserialize_flatten_infer_types() {
+    let input = "actor=alice&limit=50&cursor=abc";
+
+    let err =
+        serde_urlencoded_xrpc::from_str::<FlattenParams>(input).unwrap_err();
+    assert!(err.to_string().contains("expected u32"));
+
+    let err = Options::new()
+        .infer_types(true)
+        .from_str::<FlattenParams>("actor=alice&cursor=1700000000")
+        .unwrap_err();
+    assert!(err.to_string().contains("expected a string"));
+
+    assert_eq!(
+        Options::new().infer_types(true).from_str(input),
+        Ok(FlattenParams {
+            actor: "alice".to_owned(),
+            pagination: Pagination {
+                limit: Some(50),
+                cursor: Some("abc".to_owned()),
+            },
+        })
+    );
+}
+
+#[test]
+fn deserialize_flatten_buffered() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        q: String,
+        #[serde(flatten, deserialize_with = "buffered::deserialize")]
+        inner: Inner,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Inner {
+        uris: Vec<String>,
+        limit: Option<u32>,
+        cursor: Option<String>,
+    }
+
+    let params = |uris: &[&str], limit, cursor: Option<&str>| Params {
+        q: "x".to_owned(),
+        inner: Inner {
+            uris: uris.iter().map(|uri| uri.to_string()).collect(),
+            limit,
+            cursor: cursor.map(str::to_owned),
+        },
+    };
+
+    for options in &[Options::new(), Options::new().infer_types(true)] {
+        assert_eq!(
+            options.from_str("q=x&uris=a"),
+            Ok(params(&["a"], None, None))
+        );
+        assert_eq!(
+            options.from_str("q=x&uris=123&limit=5&cursor=1700000000"),
+            Ok(params(&["123"], Some(5), Some("1700000000")))
+        );
+        assert_eq!(
+            options.from_str("uris=a&q=x&uris=b&cursor=true"),
+            Ok(params(&["a", "b"], None, Some("true")))
+        );
+
+        let err = options
+            .from_str::<Params>("q=x&uris=a&limit=many")
+            .unwrap_err();
+        assert_eq!(err.key(), Some("limit"));
+        assert!(err.to_string().starts_with("field `limit`: "));
+    }
+
+    let options = Options::new().seq_style(SeqStyle::CommaSeparated);
+    assert_eq!(
+        options.from_str("q=x&uris=a,b&limit=5"),
+        Ok(params(&["a", "b"], Some(5), None))
+    );
+}
+
+#[test]
+fn deserialize_flatten_buffered_options() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        q: String,
+        #[serde(flatten, deserialize_with = "buffered::deserialize")]
+        inner: Inner,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Inner {
+        limit: Option<u32>,
+        #[serde(default)]
+        reverse: bool,
+        cursor: Option<String>,
+    }
+
+    let options = Options::new().infer_types(true).strict_scalars(true);
+    let err = options.from_str::<Params>("q=x&limit=05").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+    assert_eq!(err.key(), Some("limit"));
+    assert_eq!(err.value(), Some("05"));
+
+    let err = Options::new()
+        .from_str::<Params>("q=x&limit=5&limit=6")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicateField);
+    assert_eq!(err.key(), Some("limit"));
+
+    let err = Options::new()
+        .from_str::<Params>("q=x&reverse=yes")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidBool);
+    assert_eq!(err.key(), Some("reverse"));
+    assert_eq!(err.index(), None);
+
+    let options = Options::new().lenient_bools(true).empty_as_none(true);
+    assert_eq!(
+        options.from_str("q=x&reverse=yes&cursor="),
+        Ok(Params {
+            q: "x".to_owned(),
+            inner: Inner {
+                limit: None,
+                reverse: true,
+                cursor: None,
+            },
+        })
+    );
+}
+
+#[test]
+fn deserialize_flatten_buffered_map() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        limit: u32,
+        #[serde(flatten, deserialize_with = "buffered::deserialize")]
+        extra: BTreeMap<String, Vec<String>>,
+    }
+
+    let mut extra = BTreeMap::new();
+    extra.insert("a".to_owned(), vec!["1".to_owned()]);
+    extra.insert("b".to_owned(), vec!["2".to_owned(), "3".to_owned()]);
+    for options in &[Options::new(), Options::new().infer_types(true)] {
+        assert_eq!(
+            options.from_str("limit=5&a=1&b=2&b=3"),
+            Ok(Params {
+                limit: 5,
+                extra: extra.clone(),
+            })
+        );
+    }
+}
+
+#[test]
+fn deserialize_untagged_infer_types() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(untagged)]
+    enum Query {
+        Numbered { page: u32, reverse: bool },
+        Cursor { cursor: String },
+    }
+
+    let options = Options::new().infer_types(true);
+    assert_eq!(
+        options.from_str("page=2&reverse=false"),
+        Ok(Query::Numbered {
+            page: 2,
+            reverse: false,
+        })
+    );
+    assert_eq!(
+        options.from_str("cursor=3k2a"),
+        Ok(Query::Cursor {
+            cursor: "3k2a".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn deserialize_internally_tagged_infer_types() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(tag = "type", rename_all = "camelCase")]
+    enum Query {
+        Posts {
+            limit: i64,
+            #[serde(deserialize_with = "buffered::deserialize")]
+            ids: Vec<u32>,
+        },
+        Actors {
+            #[serde(deserialize_with = "buffered::deserialize")]
+            q: String,
+        },
+    }
+
+    let options = Options::new().infer_types(true);
+    assert_eq!(
+        options.from_str("type=posts&limit=-1&ids=1&ids=2"),
+        Ok(Query::Posts {
+            limit: -1,
+            ids: vec![1, 2],
+        })
+    );
+    assert_eq!(
+        options.from_str("type=posts&limit=-1&ids=1"),
+        Ok(Query::Posts {
+            limit: -1,
+            ids: vec![1],
+        })
+    );
+    assert_eq!(
+        options.from_str("type=actors&q=42"),
+        Ok(Query::Actors { q: "42".to_owned() })
+    );
+    assert_eq!(
+        options.from_str("type=actors&q=007"),
+        Ok(Query::Actors {
+            q: "007".to_owned()
+        })
+    );
+
+    let err = options
+        .from_str::<Query>("type=posts&limit=-1&ids=1&ids=x")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+    assert_eq!(err.value(), Some("x"));
+}
+
+#[test]
+fn deserialize_infer_types_keeps_hinted_strings() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        cursor: String,
+        reverse: bool,
+    }
+
+    assert_eq!(
+        Options::new()
+            .infer_types(true)
+            .from_str("cursor=123&reverse=true"),
+        Ok(Params {
+            cursor: "123".to_owned(),
+            reverse: true,
+        })
+    );
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Paginated {
+    #[serde(default)]
+    limit: Bounded<1, 100, 50>,
+    offset: Option<Bounded<0, { i64::MAX }>>,
+}
+
+#[test]
+fn deserialize_bounded() {
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str("limit=100&offset=0"),
+        Ok(Paginated {
+            limit: Bounded::new(100).unwrap(),
+            offset: Some(Bounded::new(0).unwrap()),
+        })
+    );
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str(""),
+        Ok(Paginated {
+            limit: Bounded::new(50).unwrap(),
+            offset: None,
+        })
+    );
+}
+
+#[test]
+fn deserialize_bounded_out_of_range() {
+    let err =
+        serde_urlencoded_xrpc::from_str::<Paginated>("limit=0").unwrap_err();
+    assert_eq!(err.key(), Some("limit"));
+    assert_eq!(
+        err.to_string(),
+        "field `limit`: 0 is out of range, expected 1 to 100"
+    );
+
+    let err =
+        serde_urlencoded_xrpc::from_str::<Paginated>("offset=-1").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field `offset`: -1 is out of range, expected 0 to 9223372036854775807"
+    );
+
+    let err =
+        serde_urlencoded_xrpc::from_str::<Paginated>("limit=ten").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Filter {
+    PostsWithReplies,
+    #[serde(rename = "posts_and_author_threads")]
+    AuthorThreads,
+}
+
+#[test]
+fn deserialize_open_enum() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        filter: OpenEnum<Filter>,
+        sort: Option<OpenEnum<Filter>>,
+    }
+
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str(
+            "filter=posts_and_author_threads&sort=newest"
+        ),
+        Ok(Params {
+            filter: OpenEnum::Known(Filter::AuthorThreads),
+            sort: Some(OpenEnum::Other("newest".to_owned())),
+        })
+    );
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str("filter=PostsWithReplies"),
+        Ok(Params {
+            filter: OpenEnum::Other("PostsWithReplies".to_owned()),
+            sort: None,
+        })
+    );
+}
+
+#[test]
+fn deserialize_open_enum_sequence() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        filter: Vec<OpenEnum<Filter>>,
+    }
+
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str("filter=posts_with_replies&filter="),
+        Ok(Params {
+            filter: vec![
+                OpenEnum::Known(Filter::PostsWithReplies),
+                OpenEnum::Other(String::new()),
+            ],
+        })
+    );
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+enum Window {
+    #[serde(rename = "24h")]
+    Day,
+    #[serde(rename = "7d")]
+    Week,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Sort {
+    New,
+    Top(Window),
+    Range(u32, u32),
+    Near { radius: u32, actor: String },
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct SortParams {
+    sort: Sort,
+}
+
+#[test]
+fn deserialize_data_variants() {
+    let sort = |query| {
+        serde_urlencoded_xrpc::from_str::<SortParams>(query).map(|p| p.sort)
+    };
+
+    assert_eq!(sort("sort=new"), Ok(Sort::New));
+    assert_eq!(sort("sort=top%3A24h"), Ok(Sort::Top(Window::Day)));
+    assert_eq!(sort("sort=range:1:5"), Ok(Sort::Range(1, 5)));
+    assert_eq!(
+        sort("sort=near:10:did:plc:xyz"),
+        Ok(Sort::Near {
+            radius: 10,
+            actor: "did:plc:xyz".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn deserialize_data_variants_errors() {
+    let sort = |query| {
+        serde_urlencoded_xrpc::from_str::<SortParams>(query)
+            .unwrap_err()
+            .to_string()
+    };
+
+    assert_eq!(
+        sort("sort=new:24h"),
+        "field `sort`: expected unit variant, found data `24h`"
+    );
+    assert_eq!(
+        sort("sort=top"),
+        "field `sort`: expected data for newtype variant"
+    );
+    assert_eq!(
+        sort("sort=range:1"),
+        "field `sort`: invalid length 1, expected a variant with 2 fields"
+    );
+    assert_eq!(
+        sort("sort=range:1:x"),
+        "field `sort`: invalid digit found in string"
+    );
+}
+
+#[test]
+fn deserialize_data_variants_separator() {
+    let options = Options::new().variant_separator('.');
+
+    assert_eq!(
+        options.from_str("sort=top.7d"),
+        Ok(SortParams {
+            sort: Sort::Top(Window::Week)
+        })
+    );
+    assert_eq!(
+        options.from_str("sort=near.10.alice.test"),
+        Ok(SortParams {
+            sort: Sort::Near {
+                radius: 10,
+                actor: "alice.test".to_owned(),
+            }
+        })
+    );
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Author {
+    did: String,
+    handle: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct PostFilter {
+    author: Author,
+    tags: Vec<String>,
+    since: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct NestedParams {
+    q: String,
+    filter: Option<PostFilter>,
+    #[serde(default)]
+    authors: Vec<Author>,
+    #[serde(default)]
+    extra: BTreeMap<String, u32>,
+}
+
+#[test]
+fn deserialize_nested_dot() {
+    let options = Options::new().nesting(Some(Nesting::Dot));
+
+    assert_eq!(
+        options.from_str(
+            "q=cats&filter.author.did=did:plc:x&filter.tags=a\
+             &filter.tags=b&authors.1.did=b&authors.0.did=a\
+             &authors.0.handle=a.test&extra.x=1"
+        ),
+        Ok(NestedParams {
+            q: "cats".to_owned(),
+            filter: Some(PostFilter {
+                author: Author {
+                    did: "did:plc:x".to_owned(),
+                    handle: None,
+                },
+                tags: vec!["a".to_owned(), "b".to_owned()],
+                since: None,
+            }),
+            authors: vec![
+                Author {
+                    did: "a".to_owned(),
+                    handle: Some("a.test".to_owned()),
+                },
+                Author {
+                    did: "b".to_owned(),
+                    handle: None,
+                },
+            ],
+            extra: vec![("x".to_owned(), 1)].into_iter().collect(),
+        })
+    );
+}
+
+#[test]
+fn deserialize_nested_brackets() {
+    let expected = || NestedParams {
+        q: "cats".to_owned(),
+        filter: None,
+        authors: vec![
+            Author {
+                did: "a".to_owned(),
+                handle: Some("a.test".to_owned()),
+            },
+            Author {
+                did: "b".to_owned(),
+                handle: None,
+            },
+        ],
+        extra: BTreeMap::new(),
+    };
+
+    for nesting in [Nesting::Bracket, Nesting::IndexedBracket] {
+        let options = Options::new().nesting(Some(nesting));
+        assert_eq!(
+            options.from_str(
+                "q=cats&authors%5B0%5D%5Bdid%5D=a\
+                 &authors[0][handle]=a.test&authors[1][did]=b"
+            ),
+            Ok(expected())
+        );
+        assert_eq!(
+            options.from_str(
+                "q=cats&authors[][did]=a&authors[][handle]=a.test\
+                 &authors[][did]=b"
+            ),
+            Ok(expected())
+        );
+    }
+}
+
+#[test]
+fn deserialize_nested_literal_keys() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        #[serde(rename = "filter.author")]
+        author: String,
+        #[serde(rename = "a[b")]
+        bracket: String,
+    }
+
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str("filter.author=x&a[b=y"),
+        Ok(Params {
+            author: "x".to_owned(),
+            bracket: "y".to_owned(),
+        })
+    );
+    assert_eq!(
+        Options::new()
+            .nesting(Some(Nesting::Bracket))
+            .from_str("filter.author=x&a[b=y"),
+        Ok(Params {
+            author: "x".to_owned(),
+            bracket: "y".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn deserialize_nested_errors() {
+    let options = Options::new().nesting(Some(Nesting::Dot));
+
+    let err = options
+        .from_str::<NestedParams>("q=x&filter=1&filter.tags=a")
+        .unwrap_err();
+    assert_eq!(err.key(), Some("filter.tags"));
+    assert_eq!(err.index(), Some(2));
+    assert_eq!(
+        err.to_string(),
+        "field `filter.tags`: key conflicts with the nesting of a previous key"
+    );
+
+    let err = options
+        .from_str::<NestedParams>("q=x&filter.author.did=a&filter.since=x")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+    assert_eq!(err.key(), Some("filter.since"));
+
+    let err = options
+        .from_str::<NestedParams>("q=x&authors.first.did=a")
+        .unwrap_err();
+    assert_eq!(err.to_string(), "expected an index, found `first`");
+
+    let err = options
+        .max_depth(2)
+        .from_str::<NestedParams>("q=x&filter.author.did=a")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::LimitExceeded(Limit::Depth));
+    assert_eq!(err.key(), Some("filter.author.did"));
+}
+
+#[test]
+fn deserialize_nested_deny_unknown_fields() {
+    let options = Options::new()
+        .nesting(Some(Nesting::Dot))
+        .deny_unknown_fields(true);
+
+    let err = options
+        .from_str::<NestedParams>("q=x&filter.author.did=a&filter.author.x=1")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnknownField);
+    assert_eq!(err.key(), Some("x"));
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Tags {
+    tags: Vec<String>,
+    limit: u32,
+}
+
+fn tags(tags: &[&str]) -> Tags {
+    Tags {
+        tags: tags.iter().map(|&tag| tag.to_owned()).collect(),
+        limit: 5,
+    }
+}
+
+#[test]
+fn deserialize_seq_styles() {
+    let cases = [
+        (SeqStyle::Repeated, "tags=a&tags=b&limit=5"),
+        (SeqStyle::CommaSeparated, "tags=a%2Cb&limit=5"),
+        (SeqStyle::Bracketed, "tags%5B%5D=a&tags[]=b&limit=5"),
+        (SeqStyle::Indexed, "tags[1]=b&limit=5&tags[0]=a"),
+    ];
+    for &(style, query) in &cases {
+        let result = Options::new().seq_style(style).from_str(query);
+        assert_eq!(result, Ok(tags(&["a", "b"])), "{:?}", style);
+    }
+
+    let result = Options::new()
+        .seq_style(SeqStyle::CommaSeparated)
+        .from_str("tags=&limit=5");
+    assert_eq!(result, Ok(tags(&[""])));
+
+    // Element keys are grouped with struct fields whatever the style.
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str("tags=a&tags[]=b&limit=5"),
+        Ok(tags(&["a", "b"]))
+    );
+}
+
+#[test]
+fn deserialize_seq_style_map() {
+    let query = "tags[]=a&tags[]=b&x=1";
+    let map: BTreeMap<String, Vec<String>> =
+        serde_urlencoded_xrpc::from_str(query).unwrap();
+    assert_eq!(map.get("tags[]").map(Vec::len), Some(2));
+
+    let map: BTreeMap<String, Vec<String>> = Options::new()
+        .seq_style(SeqStyle::Bracketed)
+        .from_str(query)
+        .unwrap();
+    assert_eq!(map["tags"], ["a", "b"]);
+    assert_eq!(map["x"], ["1"]);
+}
+
+#[test]
+fn deserialize_seq_style_fields() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        #[serde(with = "seq_style::comma_separated")]
+        langs: Vec<String>,
+        #[serde(with = "seq_style::indexed")]
+        ids: Vec<u32>,
+        #[serde(default, with = "seq_style::comma_separated")]
+        uris: Option<Vec<String>>,
+    }
+
+    let params: Params = serde_urlencoded_xrpc::from_str(
+        "langs=en,fr&ids[1]=2&ids[0]=1&uris=x,y",
+    )
+    .unwrap();
+    assert_eq!(
+        params,
+        Params {
+            langs: vec!["en".to_owned(), "fr".to_owned()],
+            ids: vec![1, 2],
+            uris: Some(vec!["x".to_owned(), "y".to_owned()]),
+        }
+    );
+
+    let err = serde_urlencoded_xrpc::from_str::<Params>("langs=en&ids[0]=x")
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+    assert_eq!(err.key(), Some("ids"));
+    assert_eq!(err.index(), Some(1));
+
+    let err =
+        serde_urlencoded_xrpc::from_str::<Params>("langs=en,x&ids=1&ids=y")
+            .unwrap_err();
+    assert_eq!(err.index(), Some(2));
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Encoded {
+    #[serde(with = "with::comma_joined")]
+    tags: Vec<String>,
+    #[serde(with = "with::space_joined")]
+    scopes: BTreeSet<u32>,
+    #[serde(with = "with::display_fromstr")]
+    addr: std::net::Ipv4Addr,
+}
+
+#[test]
+fn deserialize_with_modules() {
+    let encoded: Encoded = serde_urlencoded_xrpc::from_str(
+        "tags=a%2C%2Cb&scopes=2+1&addr=127.0.0.1",
+    )
+    .unwrap();
+    assert_eq!(encoded.tags, ["a", "", "b"]);
+    assert_eq!(encoded.scopes.into_iter().collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(encoded.addr, std::net::Ipv4Addr::LOCALHOST);
+
+    let encoded: Encoded =
+        serde_urlencoded_xrpc::from_str("tags=&scopes=&addr=127.0.0.1")
+            .unwrap();
+    assert!(encoded.tags.is_empty());
+    assert!(encoded.scopes.is_empty());
+}
+
+#[test]
+fn deserialize_with_modules_errors() {
+    let err = serde_urlencoded_xrpc::from_str::<Encoded>(
+        "tags=a&scopes=1+x&addr=127.0.0.1",
+    )
+    .unwrap_err();
+    assert_eq!(err.key(), Some("scopes"));
+    assert_eq!(err.index(), Some(1));
+    assert_eq!(
+        err.to_string(),
+        "field `scopes`: invalid digit found in string"
+    );
+
+    let err = serde_urlencoded_xrpc::from_str::<Encoded>(
+        "tags=a&scopes=1&addr=localhost",
+    )
+    .unwrap_err();
+    assert_eq!(err.key(), Some("addr"));
+    assert_eq!(err.to_string(), "field `addr`: invalid IPv4 address syntax");
+
+    let err = serde_urlencoded_xrpc::from_str::<Encoded>(
+        "tags=a&tags=b&scopes=1&addr=127.0.0.1",
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicateField);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn deserialize_with_json() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        #[serde(with = "with::json")]
+        filter: BTreeMap<String, Vec<u32>>,
+    }
+
+    let params: Params =
+        serde_urlencoded_xrpc::from_str("filter=%7B%22ids%22%3A%5B1%2C2%5D%7D")
+            .unwrap();
+    assert_eq!(params.filter["ids"], [1, 2]);
+
+    let err =
+        serde_urlencoded_xrpc::from_str::<Params>("filter=%7B").unwrap_err();
+    assert_eq!(err.key(), Some("filter"));
+    assert_eq!(
+        err.to_string(),
+        "field `filter`: invalid JSON: EOF while parsing an object at line 1 \
+         column 1"
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn deserialize_json() {
+    use serde_urlencoded_xrpc::json::Json;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        cursor: Json<Option<(u32, String)>>,
+        #[serde(default)]
+        extra: Option<Json<Vec<bool>>>,
+    }
+
+    let params: Params = serde_urlencoded_xrpc::from_str(
+        "cursor=%5B3%2C%22x%26y%22%5D&extra=[true]",
+    )
+    .unwrap();
+    assert_eq!(params.cursor, Json(Some((3, "x&y".to_owned()))));
+    assert_eq!(params.extra, Some(Json(vec![true])));
+
+    let params: Params =
+        serde_urlencoded_xrpc::from_str("cursor=null").unwrap();
+    assert_eq!(params.cursor.into_inner(), None);
+
+    let err =
+        serde_urlencoded_xrpc::from_str::<Params>("cursor=[3]").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Custom);
+    assert_eq!(err.key(), Some("cursor"));
+    assert_eq!(err.index(), Some(0));
+    assert_eq!(
+        err.to_string(),
+        "field `cursor`: invalid JSON: invalid length 1, expected a tuple of \
+         size 2 at line 1 column 3"
+    );
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Flags {
+    cursor: Option<String>,
+    limit: Option<u32>,
+    #[serde(default)]
+    include_pins: bool,
+    pinned: Option<bool>,
+}
+
+#[test]
+fn deserialize_empty_as_none() {
+    let err =
+        serde_urlencoded_xrpc::from_str::<Flags>("cursor=&limit=").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+    assert_eq!(err.key(), Some("limit"));
+
+    let flags: Flags = serde_urlencoded_xrpc::from_str("cursor=").unwrap();
+    assert_eq!(flags.cursor, Some("".to_owned()));
+
+    let options = Options::new().empty_as_none(true);
+    assert_eq!(
+        options.from_str("cursor=&limit=&pinned="),
+        Ok(Flags {
+            cursor: None,
+            limit: None,
+            include_pins: false,
+            pinned: None,
+        })
+    );
+    assert_eq!(
+        options
+            .from_str::<Flags>("cursor=x&limit=5")
+            .map(|f| f.limit),
+        Ok(Some(5))
+    );
+
+    let nested = options.nesting(Some(Nesting::Dot));
+    assert_eq!(
+        nested
+            .from_str::<Flags>("cursor=")
+            .map(|flags| flags.cursor),
+        Ok(None)
+    );
+}
+
+#[test]
+fn deserialize_bare_keys_as_true() {
+    let err =
+        serde_urlencoded_xrpc::from_str::<Flags>("include_pins").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidBool);
+
+    let options = Options::new().bare_keys_as_true(true);
+    let flags: Flags = options.from_str("include_pins&pinned=").unwrap();
+    assert!(flags.include_pins);
+    assert_eq!(flags.pinned, Some(true));
+
+    let flags: Flags = options.from_str("include_pins=false").unwrap();
+    assert!(!flags.include_pins);
+    assert_eq!(flags.pinned, None);
+
+    let err = options.from_str::<Flags>("include_pins=yes").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidBool);
+    assert_eq!(err.value(), Some("yes"));
+
+    let flags: Flags = options
+        .empty_as_none(true)
+        .from_str("include_pins&pinned")
+        .unwrap();
+    assert!(flags.include_pins);
+    assert_eq!(flags.pinned, None);
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Scalars {
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    count: Option<u64>,
+    #[serde(default)]
+    small: Option<u8>,
+    #[serde(default)]
+    ratio: Option<f64>,
+    #[serde(default)]
+    flag: Option<bool>,
+}
+
+#[test]
+fn deserialize_strict_scalars() {
+    let options = Options::new().strict_scalars(true);
+
+    let scalars: Scalars =
+        options.from_str("limit=-42&count=0&small=255").unwrap();
+    assert_eq!(scalars.limit, Some(-42));
+    assert_eq!(scalars.count, Some(0));
+    assert_eq!(scalars.small, Some(255));
+
+    for query in &["limit=%2B5", "limit=007", "limit=-0", "limit=", "limit=1e3"]
+    {
+        let err = options.from_str::<Scalars>(query).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidNumber, "{}", query);
+        assert_eq!(err.key(), Some("limit"));
+        assert_eq!(
+            err.to_string(),
+            "field `limit`: expected an integer without sign or leading zeros"
+        );
+    }
+
+    let err = options
+        .from_str::<Scalars>("count=9223372036854775808")
+        .unwrap_err();
+    assert_eq!(err.value(), Some("9223372036854775808"));
+    assert_eq!(
+        err.to_string(),
+        "field `count`: integer is out of range for a 64-bit signed integer"
+    );
+
+    let err = options.from_str::<Scalars>("small=256").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+
+    let err = options.from_str::<Scalars>("ratio=1.5").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+    assert_eq!(
+        err.to_string(),
+        "field `ratio`: floats are not supported by the atproto data model"
+    );
+
+    // The default parsing follows `FromStr`.
+    let scalars: Scalars =
+        serde_urlencoded_xrpc::from_str("limit=%2B5&count=007&ratio=NaN")
+            .unwrap();
+    assert_eq!(scalars.limit, Some(5));
+    assert_eq!(scalars.count, Some(7));
+    assert!(scalars.ratio.unwrap().is_nan());
+}
+
+#[test]
+fn deserialize_lenient_bools() {
+    let options = Options::new().lenient_bools(true);
+    let cases = [
+        ("true", true),
+        ("TRUE", true),
+        ("1", true),
+        ("yes", true),
+        ("Yes", true),
+        ("false", false),
+        ("False", false),
+        ("0", false),
+        ("no", false),
+        ("NO", false),
+    ];
+    for &(value, expected) in &cases {
+        let scalars: Scalars =
+            options.from_str(&format!("flag={}", value)).unwrap();
+        assert_eq!(scalars.flag, Some(expected), "{}", value);
+    }
+
+    let err = options.from_str::<Scalars>("flag=on").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidBool);
+    assert_eq!(err.value(), Some("on"));
+
+    let err =
+        serde_urlencoded_xrpc::from_str::<Scalars>("flag=TRUE").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidBool);
+}
+
+#[test]
+fn deserialize_query_map() {
+    let map: QueryMap =
+        serde_urlencoded_xrpc::from_str("tag=a&limit=5&tag=b&empty=").unwrap();
+    let pairs: Vec<(String, String)> = map.clone().into();
+    assert_eq!(
+        pairs,
+        [
+            ("tag".to_owned(), "a".to_owned()),
+            ("limit".to_owned(), "5".to_owned()),
+            ("tag".to_owned(), "b".to_owned()),
+            ("empty".to_owned(), "".to_owned()),
+        ]
+    );
+    assert_eq!(map.get("limit"), Some("5"));
+    assert_eq!(map.get("missing"), None);
+    assert_eq!(map.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+
+    let map: QueryMap = serde_urlencoded_xrpc::from_str("").unwrap();
+    assert!(map.is_empty());
+}
+
+#[test]
+fn deserialize_query_map_values_per_key() {
+    let options = Options::new().max_values_per_key(2);
+    let map: QueryMap = options.from_str("u=1&v=1&u=2").unwrap();
+    assert_eq!(map.len(), 3);
+
+    let err = options.from_str::<QueryMap>("u=1&u=2&v=1&u=3").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::LimitExceeded(Limit::ValuesPerKey));
+    assert_eq!(err.key(), Some("u"));
+    assert_eq!(err.index(), Some(3));
+}
+
+#[test]
+fn deserialize_query_map_flatten() {
+    #[derive(Deserialize, Debug)]
+    struct Params {
+        limit: u32,
+        #[serde(flatten)]
+        rest: QueryMap,
+    }
+
+    let options = Options::new().infer_types(true);
+    let params: Params =
+        options.from_str("tag=a&limit=5&tag=b&flag=true").unwrap();
+    assert_eq!(params.limit, 5);
+    assert_eq!(
+        params.rest.iter().collect::<Vec<_>>(),
+        [("tag", "a"), ("tag", "b"), ("flag", "true")]
+    );
+}
+
+#[test]
+fn deserialize_query_value() {
+    let query = "limit=5&reverse=true&tag=a&tag=007&big=18446744073709551615";
+    let params: BTreeMap<String, QueryValue> =
+        serde_urlencoded_xrpc::from_str(query).unwrap();
+    assert_eq!(params["limit"], QueryValue::from("5"));
+    assert_eq!(params["reverse"], QueryValue::from("true"));
+    assert_eq!(params["tag"], QueryValue::from(vec!["a", "007"]));
+
+    let params: BTreeMap<String, QueryValue> =
+        Options::new().infer_types(true).from_str(query).unwrap();
+    assert_eq!(params["limit"].as_i64(), Some(5));
+    assert_eq!(params["reverse"].as_bool(), Some(true));
+    assert_eq!(
+        params["tag"].as_list(),
+        Some(&[QueryValue::from("a"), QueryValue::from("007")][..])
+    );
+    assert_eq!(params["big"].as_str(), Some("18446744073709551615"));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn query_value_json() {
+    use serde_json::{json, Value};
+    use std::convert::TryFrom;
+
+    let value = QueryValue::from(vec![
+        QueryValue::from("a"),
+        QueryValue::from(-3),
+        QueryValue::from(false),
+    ]);
+    let json = Value::from(value.clone());
+    assert_eq!(json, json!(["a", -3, false]));
+    assert_eq!(QueryValue::try_from(json), Ok(value));
+
+    let err = QueryValue::try_from(json!(1.5)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "JSON number is not an integer in the i64 range"
+    );
+    let err = QueryValue::try_from(json!(["a", null])).unwrap_err();
+    assert_eq!(err.to_string(), "JSON null has no query value equivalent");
+    assert!(QueryValue::try_from(json!({})).is_err());
+}
@@ -0,0 +1,223 @@
+use serde_derive::{Deserialize, Serialize};
+use serde_urlencoded_xrpc::de::ErrorKind;
+use serde_urlencoded_xrpc::formats::{
+    AtIdentifier, AtUri, Cid, Datetime, Did, Handle, Language, Nsid, RecordKey,
+    Tid,
+};
+use std::fmt::Debug;
+use std::str::FromStr;
+
+macro_rules! syntax_vectors {
+    ($name:literal) => {
+        include_str!(concat!("interop/syntax/", $name, "_syntax_valid.txt"))
+    };
+    ($name:literal invalid) => {
+        include_str!(concat!("interop/syntax/", $name, "_syntax_invalid.txt"))
+    };
+}
+
+/// Returns the values listed in an interop vector file.
+fn vectors(file: &str) -> impl Iterator<Item = &str> {
+    file.lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn check_vectors<T>(valid: &str, invalid: &str)
+where
+    T: FromStr<Err = serde_urlencoded_xrpc::formats::Error> + Debug,
+{
+    for value in vectors(valid) {
+        if let Err(err) = value.parse::<T>() {
+            panic!("rejected valid {:?}: {}", value, err);
+        }
+    }
+    for value in vectors(invalid) {
+        if let Ok(parsed) = value.parse::<T>() {
+            panic!("accepted invalid {:?} as {:?}", value, parsed);
+        }
+    }
+}
+
+#[test]
+fn did_interop_vectors() {
+    check_vectors::<Did>(
+        syntax_vectors!("did"),
+        syntax_vectors!("did" invalid),
+    );
+}
+
+#[test]
+fn handle_interop_vectors() {
+    check_vectors::<Handle>(
+        syntax_vectors!("handle"),
+        syntax_vectors!("handle" invalid),
+    );
+}
+
+#[test]
+fn at_identifier_interop_vectors() {
+    // Some malformed DIDs, like `did.method.val`, are well-formed handles.
+    check_vectors::<AtIdentifier>(syntax_vectors!("did"), "");
+    check_vectors::<AtIdentifier>(
+        syntax_vectors!("handle"),
+        syntax_vectors!("handle" invalid),
+    );
+}
+
+#[test]
+fn nsid_interop_vectors() {
+    check_vectors::<Nsid>(
+        syntax_vectors!("nsid"),
+        syntax_vectors!("nsid" invalid),
+    );
+}
+
+#[test]
+fn at_uri_interop_vectors() {
+    check_vectors::<AtUri>(
+        syntax_vectors!("aturi"),
+        syntax_vectors!("aturi" invalid),
+    );
+}
+
+#[test]
+fn cid_interop_vectors() {
+    check_vectors::<Cid>(
+        syntax_vectors!("cid"),
+        syntax_vectors!("cid" invalid),
+    );
+}
+
+#[test]
+fn tid_interop_vectors() {
+    check_vectors::<Tid>(
+        syntax_vectors!("tid"),
+        syntax_vectors!("tid" invalid),
+    );
+}
+
+#[test]
+fn record_key_interop_vectors() {
+    check_vectors::<RecordKey>(
+        syntax_vectors!("recordkey"),
+        syntax_vectors!("recordkey" invalid),
+    );
+}
+
+#[test]
+fn datetime_interop_vectors() {
+    check_vectors::<Datetime>(
+        syntax_vectors!("datetime"),
+        syntax_vectors!("datetime" invalid),
+    );
+}
+
+#[test]
+fn language_interop_vectors() {
+    check_vectors::<Language>(
+        syntax_vectors!("language"),
+        syntax_vectors!("language" invalid),
+    );
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct GetRecord {
+    repo: AtIdentifier,
+    collection: Nsid,
+    rkey: RecordKey,
+    cid: Option<Cid>,
+}
+
+#[test]
+fn formats_round_trip() {
+    let query = "repo=did%3Aplc%3A7iza6de2dwap2sbkpav7c6c6\
+                 &collection=app.bsky.feed.post&rkey=3jzfcijpj2z2a";
+    let params: GetRecord = serde_urlencoded_xrpc::from_str(query).unwrap();
+
+    assert_eq!(
+        params,
+        GetRecord {
+            repo: Did::new("did:plc:7iza6de2dwap2sbkpav7c6c6").unwrap().into(),
+            collection: Nsid::new("app.bsky.feed.post").unwrap(),
+            rkey: RecordKey::new("3jzfcijpj2z2a").unwrap(),
+            cid: None,
+        }
+    );
+    assert_eq!(serde_urlencoded_xrpc::to_string(&params).unwrap(), query);
+}
+
+#[test]
+fn formats_sequences() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct GetProfiles {
+        actors: Vec<AtIdentifier>,
+    }
+
+    let params: GetProfiles = serde_urlencoded_xrpc::from_str(
+        "actors=alice.bsky.social&actors=did:web:example.com",
+    )
+    .unwrap();
+
+    assert_eq!(
+        params.actors,
+        vec![
+            AtIdentifier::Handle(Handle::new("alice.bsky.social").unwrap()),
+            AtIdentifier::Did(Did::new("did:web:example.com").unwrap()),
+        ]
+    );
+}
+
+#[test]
+fn formats_reject_malformed_values() {
+    let err = serde_urlencoded_xrpc::from_str::<GetRecord>(
+        "repo=alice.test&collection=app.bsky&rkey=self",
+    )
+    .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::Custom);
+    assert_eq!(err.key(), Some("collection"));
+    assert_eq!(
+        err.to_string(),
+        "field `collection`: invalid nsid: must have at least three segments"
+    );
+}
+
+#[test]
+fn formats_error_names_format() {
+    let err = Tid::new("3jzfcijpj2z21").unwrap_err();
+
+    assert_eq!(err.format(), "tid");
+    assert_eq!(
+        err.to_string(),
+        "invalid tid: must use the base32-sortable alphabet"
+    );
+    assert_eq!(
+        AtIdentifier::new("did:method:").unwrap_err().format(),
+        "at-identifier"
+    );
+}
+
+#[test]
+fn formats_at_identifier_as_str() {
+    use std::collections::{BTreeSet, HashSet};
+    use std::convert::TryFrom;
+
+    let handle = AtIdentifier::try_from("alice.bsky.social").unwrap();
+    let did =
+        AtIdentifier::try_from("did:plc:z72i7hdynmk6r22z27h6tvur").unwrap();
+    assert!(AtIdentifier::try_from("alice").is_err());
+
+    assert_eq!(handle.len(), 17);
+    assert!(did.starts_with("did:plc:"));
+
+    let set: HashSet<_> =
+        vec![handle.clone(), did.clone()].into_iter().collect();
+    assert!(set.contains("alice.bsky.social"));
+    let set: BTreeSet<_> = vec![did, handle].into_iter().collect();
+    assert!(set.contains("did:plc:z72i7hdynmk6r22z27h6tvur"));
+    let order: Vec<&str> = set.iter().map(|id| id.as_str()).collect();
+    assert_eq!(
+        order,
+        ["alice.bsky.social", "did:plc:z72i7hdynmk6r22z27h6tvur"]
+    );
+}
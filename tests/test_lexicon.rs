@@ -0,0 +1,336 @@
+#![cfg(feature = "lexicon")]
+
+use serde_urlencoded_xrpc::de::Options;
+use serde_urlencoded_xrpc::lexicon::{
+    Lexicons, MethodKind, Parameters, PropertyType, ViolationKind,
+};
+use serde_urlencoded_xrpc::query_value::QueryValue;
+
+fn lexicons() -> Lexicons {
+    Lexicons::load_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/lexicons"))
+        .unwrap()
+}
+
+fn parameters(nsid: &str) -> Parameters {
+    lexicons().get(nsid).unwrap().parameters.clone()
+}
+
+/// Returns the kind and message of each violation of `query`.
+fn violations(nsid: &str, query: &str) -> Vec<(ViolationKind, String)> {
+    parameters(nsid)
+        .validate(query)
+        .unwrap_err()
+        .into_iter()
+        .map(|violation| (violation.kind(), violation.to_string()))
+        .collect()
+}
+
+#[test]
+fn load_dir() {
+    let lexicons = lexicons();
+    let ids: Vec<_> = lexicons.iter().map(|method| &method.id[..]).collect();
+    assert_eq!(
+        ids,
+        [
+            "app.bsky.actor.getProfiles",
+            "app.bsky.feed.getAuthorFeed",
+            "app.bsky.feed.searchPosts",
+            "com.example.ping",
+        ],
+    );
+    assert!(lexicons.get("app.bsky.feed.post").is_none());
+
+    let ping = lexicons.get("com.example.ping").unwrap();
+    assert_eq!(ping.kind, MethodKind::Procedure);
+    assert_eq!(ping.parameters, Parameters::default());
+
+    let feed = lexicons.get("app.bsky.feed.getAuthorFeed").unwrap();
+    assert_eq!(feed.kind, MethodKind::Query);
+    assert!(feed.parameters.is_required("actor"));
+    assert!(!feed.parameters.is_required("limit"));
+    match &feed.parameters.get("limit").unwrap().ty {
+        PropertyType::Integer {
+            minimum, maximum, ..
+        } => assert_eq!((*minimum, *maximum), (Some(1), Some(100))),
+        ty => panic!("unexpected type {:?}", ty),
+    }
+}
+
+#[test]
+fn load_errors() {
+    let mut lexicons = Lexicons::new();
+    let err = lexicons.add_json("{").unwrap_err();
+    assert!(err.to_string().starts_with("invalid JSON: "), "{}", err);
+
+    let err = lexicons
+        .add_json(
+            r#"{
+                "id": "com.example.bad",
+                "defs": {
+                    "main": {
+                        "type": "query",
+                        "parameters": {
+                            "properties": { "x": { "type": "object" } }
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid lexicon: com.example.bad: parameter `x`: unsupported type \
+         `object`",
+    );
+
+    let method = |id: &str, param: &str| {
+        format!(
+            r#"{{
+                "id": "{}",
+                "defs": {{
+                    "main": {{
+                        "type": "query",
+                        "parameters": {{
+                            "properties": {{ "{}": {{ "type": "string" }} }}
+                        }}
+                    }}
+                }}
+            }}"#,
+            id, param,
+        )
+    };
+    let err = lexicons.add_json(&method("com.example.", "q")).unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("invalid lexicon: `id`: invalid nsid: "),
+        "{}",
+        err,
+    );
+    let err = lexicons
+        .add_json(&method("com.example.search", "a.b"))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid lexicon: com.example.search: invalid parameter name `a.b`",
+    );
+
+    let err = Lexicons::load_dir("tests/lexicons/missing").unwrap_err();
+    assert_eq!(err.path().unwrap().to_str(), Some("tests/lexicons/missing"));
+    assert!(lexicons.is_empty());
+}
+
+#[test]
+fn validate_ok() {
+    let params = parameters("app.bsky.feed.getAuthorFeed")
+        .validate(
+            "actor=did%3Aplc%3Aabc123&limit=100&filter=posts_no_replies\
+             &includePins=true&extra=kept",
+        )
+        .unwrap();
+    assert_eq!(params["actor"], QueryValue::from("did:plc:abc123"));
+    assert_eq!(params["limit"], QueryValue::Integer(100));
+    assert_eq!(params["includePins"], QueryValue::Bool(true));
+    assert_eq!(params["extra"], QueryValue::from("kept"));
+
+    let params = parameters("app.bsky.actor.getProfiles")
+        .validate("actors=alice.test&actors=did%3Aplc%3Aabc123")
+        .unwrap();
+    assert_eq!(
+        params["actors"],
+        QueryValue::from(vec!["alice.test", "did:plc:abc123"]),
+    );
+    let params = parameters("app.bsky.actor.getProfiles")
+        .validate("actors=alice.test&x=1&x=2")
+        .unwrap();
+    assert_eq!(params["actors"], QueryValue::from(vec!["alice.test"]));
+    assert_eq!(params["x"], QueryValue::from(vec!["1", "2"]));
+}
+
+#[test]
+fn validate_defaults() {
+    let params = parameters("app.bsky.feed.getAuthorFeed")
+        .validate("actor=alice.test")
+        .unwrap();
+    let names: Vec<_> = params.keys().map(String::as_str).collect();
+    assert_eq!(names, ["actor", "filter", "includePins", "limit"]);
+    assert_eq!(params["limit"], QueryValue::Integer(50));
+    assert_eq!(params["filter"], QueryValue::from("posts_with_replies"));
+    assert_eq!(params["includePins"], QueryValue::Bool(false));
+}
+
+#[test]
+fn validate_missing_and_repeated() {
+    assert_eq!(
+        violations("app.bsky.feed.getAuthorFeed", "limit=5&limit=6"),
+        [
+            (
+                ViolationKind::Missing,
+                "parameter `actor`: is required".into()
+            ),
+            (
+                ViolationKind::Repeated,
+                "parameter `limit`: is given more than once".into(),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn validate_types_and_ranges() {
+    assert_eq!(
+        violations(
+            "app.bsky.feed.getAuthorFeed",
+            "actor=alice.test&limit=0&includePins=1",
+        ),
+        [
+            (
+                ViolationKind::InvalidType,
+                "parameter `includePins`: expected `true` or `false`, found \
+                 `1`"
+                .into(),
+            ),
+            (
+                ViolationKind::OutOfRange,
+                "parameter `limit`: 0 is less than the minimum 1".into(),
+            ),
+        ],
+    );
+    assert_eq!(
+        violations("app.bsky.feed.getAuthorFeed", "actor=alice.test&limit=05"),
+        [(
+            ViolationKind::InvalidType,
+            "parameter `limit`: expected an integer, found `05`".into(),
+        )],
+    );
+}
+
+#[test]
+fn validate_strings() {
+    assert_eq!(
+        violations(
+            "app.bsky.feed.getAuthorFeed",
+            "actor=not%20a%20handle&filter=everything",
+        ),
+        [(
+            ViolationKind::InvalidFormat,
+            "parameter `actor`: invalid at-identifier: must have at least two \
+             segments"
+                .into(),
+        )],
+    );
+    let tag = "x".repeat(641);
+    let violations = parameters("app.bsky.feed.searchPosts")
+        .validate(&format!("q=rust&tag=ok&tag={}", tag))
+        .unwrap_err();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].kind(), ViolationKind::Length);
+    assert_eq!(violations[0].index(), Some(1));
+    assert_eq!(
+        violations[0].to_string(),
+        "parameter `tag`[1]: is longer than 640 bytes",
+    );
+}
+
+#[test]
+fn validate_arrays() {
+    let query: String =
+        (0..26).map(|i| format!("actors=a{}.test&", i)).collect();
+    assert_eq!(
+        violations("app.bsky.actor.getProfiles", &query),
+        [(
+            ViolationKind::ItemCount,
+            "parameter `actors`: has more than 25 items".into(),
+        )],
+    );
+    let violations = parameters("app.bsky.actor.getProfiles")
+        .validate("actors=alice.test&actors=-bad-")
+        .unwrap_err();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].param(), "actors");
+    assert_eq!(violations[0].index(), Some(1));
+    assert_eq!(violations[0].kind(), ViolationKind::InvalidFormat);
+}
+
+#[test]
+fn validate_enum_and_known_values() {
+    let mut lexicons = Lexicons::new();
+    lexicons
+        .add_json(
+            r#"{
+                "lexicon": 1,
+                "id": "com.example.list",
+                "defs": {
+                    "main": {
+                        "type": "query",
+                        "parameters": {
+                            "type": "params",
+                            "properties": {
+                                "order": {
+                                    "type": "string",
+                                    "enum": ["asc", "desc"]
+                                },
+                                "sort": {
+                                    "type": "string",
+                                    "knownValues": ["top", "latest"]
+                                },
+                                "page": { "type": "integer", "enum": [1, 2] }
+                            }
+                        }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+    let parameters = &lexicons.get("com.example.list").unwrap().parameters;
+
+    assert!(parameters.validate("order=asc&sort=oldest&page=2").is_ok());
+    let violations: Vec<_> = parameters
+        .validate("order=random&page=3")
+        .unwrap_err()
+        .into_iter()
+        .map(|violation| (violation.kind(), violation.to_string()))
+        .collect();
+    assert_eq!(
+        violations,
+        [
+            (
+                ViolationKind::UnknownValue,
+                "parameter `order`: `random` is not an accepted value".into(),
+            ),
+            (
+                ViolationKind::UnknownValue,
+                "parameter `page`: 3 is not an accepted value".into(),
+            ),
+        ],
+    );
+}
+
+#[test]
+fn validate_with_options() {
+    let parameters = parameters("app.bsky.feed.getAuthorFeed");
+    let query = "actor=alice.test&limit=%FF";
+
+    // Malformed UTF-8 is replaced by default, and then fails as an integer.
+    assert_eq!(
+        parameters.validate(query).unwrap_err()[0].kind(),
+        ViolationKind::InvalidType,
+    );
+
+    let violations = parameters
+        .validate_with(query, &Options::new().strict_decoding(true))
+        .unwrap_err();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].kind(), ViolationKind::Malformed);
+    assert!(violations[0].to_string().starts_with("malformed query: "));
+
+    let violations = parameters
+        .validate_with(query, &Options::new().max_pairs(1))
+        .unwrap_err();
+    assert_eq!(violations[0].kind(), ViolationKind::Malformed);
+    assert_eq!(violations[0].param(), "");
+    assert!(
+        violations[0].to_string().contains("pairs"),
+        "{}",
+        violations[0]
+    );
+}
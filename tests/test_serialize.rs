@@ -1,4 +1,12 @@
 use serde_derive::Serialize;
+use serde_urlencoded_xrpc::bounded::Bounded;
+use serde_urlencoded_xrpc::open_enum::OpenEnum;
+use serde_urlencoded_xrpc::query_map::QueryMap;
+use serde_urlencoded_xrpc::query_value::QueryValue;
+use serde_urlencoded_xrpc::seq_style;
+use serde_urlencoded_xrpc::ser::{Encoding, Nesting, Options, SeqStyle};
+use serde_urlencoded_xrpc::with;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 #[derive(Serialize)]
 struct NewType<T>(T);
@@ -133,7 +141,7 @@ fn serialize_map_bool() {
     );
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
 enum X {
     A,
     B,
@@ -192,3 +200,951 @@ fn serialize_struct_vec() {
         Ok("x=1&x=2&x=3&y=4&y=5&y=6".into()),
     );
 }
+
+#[test]
+fn serialize_struct_collections() {
+    #[derive(Serialize)]
+    struct Params {
+        set: BTreeSet<&'static str>,
+        deque: VecDeque<u32>,
+        array: [&'static str; 2],
+        tuple: (u32, bool),
+    }
+
+    let params = Params {
+        set: ["b", "a"].iter().copied().collect(),
+        deque: vec![1, 2].into_iter().collect(),
+        array: ["x", "y"],
+        tuple: (3, true),
+    };
+
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(params),
+        Ok(
+            "set=a&set=b&deque=1&deque=2&array=x&array=y&tuple=3&tuple=true"
+                .to_owned()
+        )
+    );
+}
+
+#[test]
+fn serialize_nested_tuple() {
+    #[derive(Serialize)]
+    struct Params {
+        pairs: Vec<(u32, u32)>,
+    }
+
+    let params = Params {
+        pairs: vec![(1, 2)],
+    };
+
+    assert!(serde_urlencoded_xrpc::to_string(params).is_err());
+}
+
+struct Bytes(&'static [u8]);
+
+impl serde::Serialize for Bytes {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+#[test]
+fn serialize_error_field() {
+    #[derive(Serialize)]
+    struct Params {
+        limit: u32,
+        filter: BTreeMap<&'static str, &'static str>,
+    }
+
+    let params = Params {
+        limit: 1,
+        filter: BTreeMap::new(),
+    };
+
+    let err = serde_urlencoded_xrpc::to_string(params).unwrap_err();
+    assert_eq!(err.key(), Some("filter"));
+    assert_eq!(err.index(), None);
+    assert_eq!(
+        err.to_string(),
+        "field `filter`: unsupported value type: map"
+    );
+}
+
+#[test]
+fn serialize_error_sequence_element() {
+    #[derive(Serialize)]
+    struct Params {
+        uris: Vec<Bytes>,
+    }
+
+    let params = Params {
+        uris: vec![Bytes(b"a"), Bytes(b"b"), Bytes(b"c"), Bytes(b"\xff")],
+    };
+
+    let err = serde_urlencoded_xrpc::to_string(params).unwrap_err();
+    assert_eq!(err.key(), Some("uris"));
+    assert_eq!(err.index(), Some(3));
+    assert!(err
+        .to_string()
+        .starts_with("field `uris`[3]: invalid UTF-8"));
+}
+
+#[test]
+fn serialize_encodings() {
+    #[derive(Serialize)]
+    struct Params {
+        uri: &'static str,
+        q: &'static str,
+    }
+
+    let params = || Params {
+        uri: "at://did:plc:xyz/app.bsky.feed.post/3k",
+        q: "a b&c=d+é~",
+    };
+
+    assert_eq!(
+        Options::new().encoding(Encoding::Form).to_string(params()),
+        serde_urlencoded_xrpc::to_string(params())
+    );
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(params()),
+        Ok("uri=at%3A%2F%2Fdid%3Aplc%3Axyz%2Fapp.bsky.feed.post%2F3k\
+            &q=a+b%26c%3Dd%2B%C3%A9%7E"
+            .to_owned())
+    );
+    assert_eq!(
+        Options::new()
+            .encoding(Encoding::Rfc3986)
+            .to_string(params()),
+        Ok("uri=at://did:plc:xyz/app.bsky.feed.post/3k\
+            &q=a%20b%26c%3Dd%2B%C3%A9~"
+            .to_owned())
+    );
+    assert_eq!(
+        Options::new()
+            .encoding(Encoding::Strict)
+            .to_string(params()),
+        Ok("uri=at%3A%2F%2Fdid%3Aplc%3Axyz%2Fapp.bsky.feed.post%2F3k\
+            &q=a%20b%26c%3Dd%2B%C3%A9~"
+            .to_owned())
+    );
+}
+
+#[test]
+fn serialize_into_form_urlencoded() {
+    #[derive(Serialize)]
+    struct Params {
+        limit: u32,
+    }
+
+    let mut urlencoder = form_urlencoded::Serializer::new("a=b".to_owned());
+    serde::Serialize::serialize(
+        &Params { limit: 1 },
+        serde_urlencoded_xrpc::Serializer::new(&mut urlencoder),
+    )
+    .unwrap();
+    assert_eq!(urlencoder.finish(), "a=b&limit=1");
+}
+
+#[derive(Serialize)]
+struct Pagination {
+    limit: Option<u32>,
+    cursor: Option<&'static str>,
+}
+
+#[test]
+fn serialize_flatten_struct() {
+    #[derive(Serialize)]
+    struct Params {
+        uris: Vec<&'static str>,
+        #[serde(flatten)]
+        pagination: Pagination,
+    }
+
+    let params = Params {
+        uris: vec!["a", "b"],
+        pagination: Pagination {
+            limit: Some(50),
+            cursor: None,
+        },
+    };
+
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(params),
+        Ok("uris=a&uris=b&limit=50".to_owned())
+    );
+}
+
+#[test]
+fn serialize_flatten_map() {
+    #[derive(Serialize)]
+    struct Params {
+        actor: &'static str,
+        #[serde(flatten)]
+        extra: BTreeMap<String, Vec<u32>>,
+    }
+
+    let mut extra = BTreeMap::new();
+    extra.insert("a".to_owned(), vec![1, 2]);
+    extra.insert("b".to_owned(), vec![]);
+    extra.insert("c".to_owned(), vec![3]);
+    let params = Params {
+        actor: "alice",
+        extra,
+    };
+
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(params),
+        Ok("actor=alice&a=1&a=2&c=3".to_owned())
+    );
+}
+
+#[test]
+fn serialize_map_keys() {
+    let mut map = BTreeMap::new();
+    map.insert(X::A, 1);
+    map.insert(X::B, 2);
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(map),
+        Ok("A=1&B=2".to_owned())
+    );
+
+    let mut map = BTreeMap::new();
+    map.insert(vec![1, 2], 1);
+    assert!(serde_urlencoded_xrpc::to_string(map).is_err());
+}
+
+#[test]
+fn serialize_bounded() {
+    #[derive(Serialize)]
+    struct Params {
+        limit: Bounded<1, 100, 50>,
+    }
+
+    let params = Params {
+        limit: Bounded::default(),
+    };
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(params),
+        Ok("limit=50".to_owned())
+    );
+
+    assert_eq!(
+        Bounded::<1, 100>::new(101).unwrap_err().to_string(),
+        "101 is out of range, expected 1 to 100"
+    );
+}
+
+#[test]
+fn serialize_open_enum() {
+    #[derive(Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Filter {
+        PostsWithReplies,
+    }
+
+    #[derive(Serialize)]
+    struct Params {
+        filter: Vec<OpenEnum<Filter>>,
+    }
+
+    let params = Params {
+        filter: vec![
+            OpenEnum::Known(Filter::PostsWithReplies),
+            OpenEnum::Other("posts_with_media".to_owned()),
+        ],
+    };
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(params),
+        Ok("filter=posts_with_replies&filter=posts_with_media".to_owned())
+    );
+}
+
+#[derive(Serialize)]
+enum Window {
+    #[serde(rename = "24h")]
+    Day,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Sort {
+    New,
+    Top(Window),
+    Range(u32, u32),
+    Near { radius: u32, actor: String },
+}
+
+#[derive(Serialize)]
+struct SortParams {
+    sort: Vec<Sort>,
+}
+
+#[test]
+fn serialize_data_variants() {
+    let params = SortParams {
+        sort: vec![
+            Sort::New,
+            Sort::Top(Window::Day),
+            Sort::Range(1, 5),
+            Sort::Near {
+                radius: 10,
+                actor: "did:plc:xyz".to_owned(),
+            },
+        ],
+    };
+
+    assert_eq!(
+        Options::new()
+            .encoding(Encoding::Rfc3986)
+            .to_string(&params),
+        Ok(
+            "sort=new&sort=top:24h&sort=range:1:5&sort=near:10:did:plc:xyz"
+                .to_owned()
+        )
+    );
+    assert_eq!(
+        Options::new()
+            .encoding(Encoding::Rfc3986)
+            .variant_separator('~')
+            .to_string(&params),
+        Ok(
+            "sort=new&sort=top~24h&sort=range~1~5&sort=near~10~did:plc:xyz"
+                .to_owned()
+        )
+    );
+}
+
+#[test]
+fn serialize_data_variants_renamed_with_separator() {
+    #[derive(Serialize, serde_derive::Deserialize, Debug, PartialEq)]
+    enum Collection {
+        #[serde(rename = "app.bsky:post")]
+        Post,
+        #[serde(rename = "app.bsky")]
+        Any(String),
+    }
+
+    #[derive(Serialize, serde_derive::Deserialize, Debug, PartialEq)]
+    struct Params {
+        collection: Vec<Collection>,
+    }
+
+    let params = Params {
+        collection: vec![Collection::Post, Collection::Any("like".to_owned())],
+    };
+    let options = Options::new().encoding(Encoding::Rfc3986);
+    let serialized = options.to_string(&params).unwrap();
+    assert_eq!(
+        serialized,
+        "collection=app.bsky:post&collection=app.bsky:like"
+    );
+    assert_eq!(
+        serde_urlencoded_xrpc::from_str::<Params>(&serialized),
+        Ok(params)
+    );
+}
+
+#[test]
+fn serialize_data_variants_ambiguous_field() {
+    #[derive(Serialize)]
+    enum Pair {
+        Pair(String, String),
+    }
+
+    #[derive(Serialize)]
+    struct Params {
+        pair: Pair,
+    }
+
+    let params = Params {
+        pair: Pair::Pair("a:b".to_owned(), "c".to_owned()),
+    };
+    let err = serde_urlencoded_xrpc::to_string(params).unwrap_err();
+    assert_eq!(err.key(), Some("pair"));
+    assert_eq!(
+        err.to_string(),
+        "field `pair`: variant field `a:b` contains the separator `:`"
+    );
+}
+
+#[derive(Serialize)]
+struct Author {
+    did: &'static str,
+    handle: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct PostFilter {
+    author: Author,
+    tags: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+struct NestedParams {
+    q: &'static str,
+    filter: Option<PostFilter>,
+    authors: Vec<Author>,
+    extra: BTreeMap<&'static str, u32>,
+}
+
+fn nested_params() -> NestedParams {
+    NestedParams {
+        q: "cats",
+        filter: Some(PostFilter {
+            author: Author {
+                did: "did:plc:x",
+                handle: None,
+            },
+            tags: vec!["a", "b"],
+        }),
+        authors: vec![
+            Author {
+                did: "a",
+                handle: Some("a.test"),
+            },
+            Author {
+                did: "b",
+                handle: None,
+            },
+        ],
+        extra: vec![("x", 1)].into_iter().collect(),
+    }
+}
+
+#[test]
+fn serialize_nested() {
+    let to_string = |nesting| {
+        Options::new()
+            .encoding(Encoding::Rfc3986)
+            .nesting(Some(nesting))
+            .to_string(nested_params())
+            .unwrap()
+    };
+
+    assert_eq!(
+        to_string(Nesting::Dot),
+        "q=cats&filter.author.did=did:plc:x&filter.tags=a&filter.tags=b\
+         &authors.0.did=a&authors.0.handle=a.test&authors.1.did=b&extra.x=1"
+    );
+    assert_eq!(
+        to_string(Nesting::Bracket),
+        "q=cats&filter%5Bauthor%5D%5Bdid%5D=did:plc:x\
+         &filter%5Btags%5D=a&filter%5Btags%5D=b\
+         &authors%5B%5D%5Bdid%5D=a&authors%5B%5D%5Bhandle%5D=a.test\
+         &authors%5B%5D%5Bdid%5D=b&extra%5Bx%5D=1"
+    );
+
+    let mut output = String::new();
+    let mut encoder = form_urlencoded::Serializer::new(&mut output);
+    let options = Options::new().nesting(Some(Nesting::IndexedBracket));
+    serde::Serialize::serialize(
+        &nested_params(),
+        serde_urlencoded_xrpc::Serializer::with_options(&mut encoder, options),
+    )
+    .unwrap();
+    encoder.finish();
+    assert_eq!(
+        form_urlencoded::parse(output.as_bytes())
+            .map(|(key, _)| key.into_owned())
+            .collect::<Vec<_>>(),
+        vec![
+            "q",
+            "filter[author][did]",
+            "filter[tags]",
+            "filter[tags]",
+            "authors[0][did]",
+            "authors[0][handle]",
+            "authors[1][did]",
+            "extra[x]",
+        ]
+    );
+}
+
+#[test]
+fn serialize_nested_dot_indexed_round_trip() {
+    use serde_urlencoded_xrpc::de;
+
+    #[derive(Serialize, serde_derive::Deserialize, Debug, PartialEq)]
+    struct Item {
+        name: String,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    #[derive(Serialize, serde_derive::Deserialize, Debug, PartialEq)]
+    struct Params {
+        items: Vec<Item>,
+    }
+
+    let params = Params {
+        items: vec![
+            Item {
+                name: "a".to_owned(),
+                tags: vec!["x".to_owned(), "y".to_owned()],
+            },
+            Item {
+                name: "b".to_owned(),
+                tags: vec![],
+            },
+        ],
+    };
+
+    let serialized = Options::new()
+        .encoding(Encoding::Rfc3986)
+        .nesting(Some(Nesting::Dot))
+        .seq_style(SeqStyle::Indexed)
+        .to_string(&params)
+        .unwrap();
+    assert_eq!(
+        serialized,
+        "items%5B0%5D.name=a&items%5B0%5D.tags%5B0%5D=x\
+         &items%5B0%5D.tags%5B1%5D=y&items%5B1%5D.name=b"
+    );
+
+    let options = de::Options::new()
+        .nesting(Some(Nesting::Dot))
+        .seq_style(SeqStyle::Indexed);
+    assert_eq!(options.from_str::<Params>(&serialized).unwrap(), params);
+}
+
+#[test]
+fn serialize_nested_disabled() {
+    let err = serde_urlencoded_xrpc::to_string(nested_params()).unwrap_err();
+    assert_eq!(err.key(), Some("filter"));
+    assert_eq!(
+        err.to_string(),
+        "field `filter`: unsupported value type: struct"
+    );
+}
+
+#[test]
+fn serialize_nested_error_key() {
+    #[derive(Serialize)]
+    struct Inner {
+        pairs: Vec<(u32, u32)>,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        inner: Inner,
+    }
+
+    let err = Options::new()
+        .nesting(Some(Nesting::Dot))
+        .to_string(Outer {
+            inner: Inner {
+                pairs: vec![(1, 2)],
+            },
+        })
+        .unwrap_err();
+    assert_eq!(err.key(), Some("inner.pairs"));
+    assert_eq!(err.index(), Some(0));
+}
+
+#[derive(Serialize)]
+struct Tags {
+    tags: Vec<&'static str>,
+    limit: u32,
+}
+
+#[test]
+fn serialize_seq_styles() {
+    let tags = Tags {
+        tags: vec!["a", "b"],
+        limit: 5,
+    };
+    let cases = [
+        (SeqStyle::Repeated, "tags=a&tags=b&limit=5"),
+        (SeqStyle::CommaSeparated, "tags=a%2Cb&limit=5"),
+        (SeqStyle::Bracketed, "tags%5B%5D=a&tags%5B%5D=b&limit=5"),
+        (SeqStyle::Indexed, "tags%5B0%5D=a&tags%5B1%5D=b&limit=5"),
+    ];
+    for &(style, expected) in &cases {
+        let query = Options::new().seq_style(style).to_string(&tags);
+        assert_eq!(query, Ok(expected.to_owned()), "{:?}", style);
+    }
+
+    let empty = Tags {
+        tags: vec![],
+        limit: 5,
+    };
+    for &(style, _) in &cases {
+        let query = Options::new().seq_style(style).to_string(&empty);
+        assert_eq!(query, Ok("limit=5".to_owned()), "{:?}", style);
+    }
+}
+
+#[test]
+fn serialize_seq_style_fields() {
+    #[derive(Serialize)]
+    struct Params {
+        #[serde(with = "seq_style::comma_separated")]
+        langs: Vec<&'static str>,
+        #[serde(with = "seq_style::bracketed")]
+        dids: Vec<&'static str>,
+        #[serde(with = "seq_style::repeated")]
+        uris: Vec<&'static str>,
+        tags: Vec<&'static str>,
+    }
+
+    let params = Params {
+        langs: vec!["en", "fr"],
+        dids: vec!["did:plc:a"],
+        uris: vec!["x", "y"],
+        tags: vec!["a"],
+    };
+    assert_eq!(
+        Options::new()
+            .seq_style(SeqStyle::Indexed)
+            .to_string(&params),
+        Ok("langs=en%2Cfr&dids%5B%5D=did%3Aplc%3Aa&uris=x&uris=y&\
+            tags%5B0%5D=a"
+            .to_owned())
+    );
+}
+
+#[test]
+fn serialize_comma_separated_errors() {
+    #[derive(Serialize)]
+    struct Params {
+        #[serde(with = "seq_style::comma_separated")]
+        tags: Vec<Option<&'static str>>,
+    }
+
+    let params = Params {
+        tags: vec![Some("a"), None, Some("b")],
+    };
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(&params),
+        Ok("tags=a%2Cb".to_owned())
+    );
+
+    let params = Params {
+        tags: vec![Some("a"), Some("b,c")],
+    };
+    let err = serde_urlencoded_xrpc::to_string(&params).unwrap_err();
+    assert_eq!(err.key(), Some("tags"));
+    assert_eq!(err.index(), Some(1));
+    assert_eq!(
+        err.to_string(),
+        "field `tags`[1]: element `b,c` contains a comma"
+    );
+}
+
+#[derive(Serialize)]
+struct Encoded {
+    #[serde(with = "with::comma_joined")]
+    tags: Vec<&'static str>,
+    #[serde(with = "with::space_joined")]
+    scopes: BTreeSet<u32>,
+    #[serde(with = "with::display_fromstr")]
+    addr: std::net::Ipv4Addr,
+}
+
+#[test]
+fn serialize_with_modules() {
+    let encoded = Encoded {
+        tags: vec!["a", "", "b"],
+        scopes: vec![2, 1].into_iter().collect(),
+        addr: std::net::Ipv4Addr::LOCALHOST,
+    };
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(&encoded),
+        Ok("tags=a%2C%2Cb&scopes=1+2&addr=127.0.0.1".to_owned())
+    );
+
+    let empty = Encoded {
+        tags: vec![],
+        scopes: BTreeSet::new(),
+        ..encoded
+    };
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(&empty),
+        Ok("tags=&scopes=&addr=127.0.0.1".to_owned())
+    );
+}
+
+#[test]
+fn serialize_with_separated_errors() {
+    let encoded = Encoded {
+        tags: vec!["a", "b,c"],
+        scopes: BTreeSet::new(),
+        addr: std::net::Ipv4Addr::LOCALHOST,
+    };
+    let err = serde_urlencoded_xrpc::to_string(&encoded).unwrap_err();
+    assert_eq!(err.key(), Some("tags"));
+    assert_eq!(
+        err.to_string(),
+        "field `tags`: element `b,c` contains the separator `,`"
+    );
+
+    let encoded = Encoded {
+        tags: vec![""],
+        ..encoded
+    };
+    let err = serde_urlencoded_xrpc::to_string(&encoded).unwrap_err();
+    assert_eq!(err.key(), Some("tags"));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn serialize_with_json() {
+    #[derive(Serialize)]
+    struct Params {
+        #[serde(with = "with::json")]
+        filter: BTreeMap<&'static str, Vec<u32>>,
+    }
+
+    let params = Params {
+        filter: vec![("ids", vec![1, 2])].into_iter().collect(),
+    };
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(&params),
+        Ok("filter=%7B%22ids%22%3A%5B1%2C2%5D%7D".to_owned())
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn serialize_json() {
+    use serde_urlencoded_xrpc::json::Json;
+
+    #[derive(Serialize)]
+    struct Params {
+        q: &'static str,
+        cursor: Json<Option<(u32, &'static str)>>,
+    }
+
+    let params = Params {
+        q: "a b",
+        cursor: Json(Some((3, "x&y"))),
+    };
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(&params),
+        Ok("q=a+b&cursor=%5B3%2C%22x%26y%22%5D".to_owned())
+    );
+
+    let params = Params {
+        q: "a",
+        cursor: Json(None),
+    };
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(&params),
+        Ok("q=a&cursor=null".to_owned())
+    );
+}
+
+#[test]
+fn serialize_none_as_empty() {
+    #[derive(Serialize)]
+    struct Params {
+        cursor: Option<&'static str>,
+        limit: Option<u32>,
+        tags: Vec<Option<&'static str>>,
+    }
+
+    let params = Params {
+        cursor: None,
+        limit: Some(5),
+        tags: vec![Some("a"), None],
+    };
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(&params),
+        Ok("limit=5&tags=a".to_owned())
+    );
+    assert_eq!(
+        Options::new().none_as_empty(true).to_string(&params),
+        Ok("cursor=&limit=5&tags=a&tags=".to_owned())
+    );
+}
+
+#[test]
+fn serialize_xrpc_strict() {
+    #[derive(Serialize)]
+    enum Sort {
+        #[serde(rename = "top")]
+        Top,
+    }
+
+    #[derive(Serialize)]
+    struct Params {
+        actor: &'static str,
+        limit: Bounded<1, 100, 50>,
+        count: u64,
+        reverse: bool,
+        sort: Sort,
+        tags: Vec<&'static str>,
+        cursor: Option<&'static str>,
+    }
+
+    let params = Params {
+        actor: "did:plc:xyz",
+        limit: Bounded::default(),
+        count: 9_223_372_036_854_775_807,
+        reverse: true,
+        sort: Sort::Top,
+        tags: vec!["a", "b"],
+        cursor: None,
+    };
+    let options = Options::new().xrpc_strict(true);
+    assert_eq!(
+        options.to_string(&params),
+        Ok("actor=did%3Aplc%3Axyz&limit=50&count=9223372036854775807&\
+            reverse=true&sort=top&tags=a&tags=b"
+            .to_owned())
+    );
+
+    let params = Params {
+        count: 9_223_372_036_854_775_808,
+        ..params
+    };
+    let err = options.to_string(&params).unwrap_err();
+    assert_eq!(err.key(), Some("count"));
+    assert_eq!(
+        err.to_string(),
+        "field `count`: integer 9223372036854775808 is out of range for the \
+         lexicon"
+    );
+}
+
+#[test]
+fn serialize_xrpc_strict_rejects() {
+    #[derive(Serialize)]
+    struct Inner {
+        a: u32,
+    }
+
+    #[derive(Serialize)]
+    enum Window {
+        Hours(u32),
+    }
+
+    fn check<T: serde::Serialize>(key: &str, value: T, type_str: &str) {
+        let mut map = BTreeMap::new();
+        map.insert(key, value);
+        let options = Options::new().xrpc_strict(true);
+        assert!(serde_urlencoded_xrpc::to_string(&map).is_ok());
+        let err = options.to_string(&map).unwrap_err();
+        assert_eq!(err.key(), Some(key));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "field `{}`: {} is not a lexicon query parameter type",
+                key, type_str
+            )
+        );
+    }
+
+    check("ratio", 1.5f64, "f64");
+    check("ratio", 1e20f32, "f32");
+    check("initial", 'a', "char");
+    check("window", Window::Hours(3), "enum variant with data");
+
+    let err = Options::new()
+        .xrpc_strict(true)
+        .nesting(Some(Nesting::Dot))
+        .to_string(NewType(BTreeMap::from([("inner", Inner { a: 1 })])))
+        .unwrap_err();
+    assert_eq!(err.key(), Some("inner"));
+    assert_eq!(
+        err.to_string(),
+        "field `inner`: struct is not a lexicon query parameter type"
+    );
+
+    let err = Options::new()
+        .xrpc_strict(true)
+        .to_string(NewType(BTreeMap::from([("ids", vec![1.5])])))
+        .unwrap_err();
+    assert_eq!(err.key(), Some("ids"));
+    assert_eq!(err.index(), Some(0));
+}
+
+#[test]
+fn serialize_xrpc_strict_tuples() {
+    let options = Options::new().xrpc_strict(true);
+
+    let map = BTreeMap::from([("uris", ["at://a", "at://b"])]);
+    assert_eq!(
+        options.to_string(&map),
+        Ok("uris=at%3A%2F%2Fa&uris=at%3A%2F%2Fb".to_owned())
+    );
+
+    #[derive(Serialize)]
+    struct Range(u32, u32);
+
+    let map = BTreeMap::from([("range", Range(1, 5))]);
+    assert_eq!(options.to_string(&map), Ok("range=1&range=5".to_owned()));
+
+    let err = options
+        .to_string(BTreeMap::from([("pair", (1, 1.5))]))
+        .unwrap_err();
+    assert_eq!(err.key(), Some("pair"));
+    assert_eq!(err.index(), Some(1));
+}
+
+#[test]
+fn serialize_query_map() {
+    let mut map: QueryMap =
+        vec![("tag", "a"), ("limit", "5"), ("tag", "b"), ("q", "x y")]
+            .into_iter()
+            .collect();
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(&map),
+        Ok("tag=a&limit=5&tag=b&q=x+y".to_owned())
+    );
+
+    assert_eq!(map.insert("tag", "c"), Some("a".to_owned()));
+    assert_eq!(map.insert("cursor", "1"), None);
+    map.append("limit", "6");
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(&map),
+        Ok("tag=c&limit=5&q=x+y&cursor=1&limit=6".to_owned())
+    );
+
+    assert_eq!(map.remove("limit"), ["5", "6"]);
+    assert!(map.remove("limit").is_empty());
+    assert!(!map.contains_key("limit"));
+    assert_eq!(map.len(), 3);
+
+    let pairs = vec![("a".to_owned(), "1".to_owned())];
+    let map = QueryMap::from(pairs.clone());
+    assert_eq!(Vec::from(map), pairs);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn serialize_query_map_json_round_trip() {
+    let map: QueryMap = vec![("tag", "a"), ("limit", "5"), ("tag", "b")]
+        .into_iter()
+        .collect();
+    let json = serde_json::to_string(&map).unwrap();
+    assert_eq!(json, r#"{"tag":"a","limit":"5","tag":"b"}"#);
+    assert_eq!(serde_json::from_str::<QueryMap>(&json).unwrap(), map);
+}
+
+#[test]
+fn serialize_query_value() {
+    let params = vec![
+        ("actor", QueryValue::from("alice")),
+        ("limit", QueryValue::from(50)),
+        ("reverse", QueryValue::from(true)),
+        ("tag", QueryValue::from(vec!["a", "b"])),
+    ];
+    let params: BTreeMap<_, _> = params.into_iter().collect();
+    assert_eq!(
+        serde_urlencoded_xrpc::to_string(&params),
+        Ok("actor=alice&limit=50&reverse=true&tag=a&tag=b".to_owned())
+    );
+
+    let nested = NewType(BTreeMap::from([(
+        "tag",
+        QueryValue::from(vec![QueryValue::from(vec!["a"])]),
+    )]));
+    let err = serde_urlencoded_xrpc::to_string(nested).unwrap_err();
+    assert_eq!(err.key(), Some("tag"));
+}